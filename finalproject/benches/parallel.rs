@@ -0,0 +1,81 @@
+//! Times the steps rayon parallelizes, k-means assignment and the distance
+//! matrix, on 10,000 synthetic municipalities: once on a single thread and
+//! once on rayon's default pool, which has one thread per core. Run with
+//! `cargo bench`; the speedup only shows on a multicore machine.
+
+use finalproject::{
+    distance_matrix, extract_features, k_means_features, EducationData, KMeansConfig,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+const POINTS: usize = 10_000;
+const CATEGORIES: i32 = 8;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(84);
+    let data: Vec<EducationData> = (0..POINTS)
+        .map(|i| {
+            let mut record = EducationData::new(format!("M{}", i));
+            for category in 1..=CATEGORIES {
+                record.set(category, 2020, rng.gen_range(0.0..100.0));
+            }
+            record
+        })
+        .collect();
+    let categories: Vec<i32> = (1..=CATEGORIES).collect();
+    let municipalities: Vec<String> = (0..POINTS).map(|i| format!("M{}", i)).collect();
+    let features = extract_features(&data, &categories);
+    let config = KMeansConfig {
+        n_init: 1,
+        max_iterations: 50,
+        ..KMeansConfig::new(8)
+    };
+
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .expect("a one-thread pool");
+    println!(
+        "{} points, {} threads available",
+        POINTS,
+        rayon::current_num_threads()
+    );
+
+    let k_means = || {
+        k_means_features(&municipalities, &features, &config).expect("k-means runs");
+    };
+    report("k-means", single.install(|| time(k_means)), time(k_means));
+
+    let matrix = || {
+        distance_matrix(&data, &categories);
+    };
+    report(
+        "distance matrix",
+        single.install(|| time(matrix)),
+        time(matrix),
+    );
+}
+
+// The best of three runs, to keep a slow first run from skewing the result
+fn time(f: impl Fn()) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .expect("three runs")
+}
+
+fn report(step: &str, serial: Duration, parallel: Duration) {
+    println!(
+        "{:<16} 1 thread: {:>8.1} ms   all threads: {:>8.1} ms   speedup: {:.2}x",
+        step,
+        serial.as_secs_f64() * 1000.0,
+        parallel.as_secs_f64() * 1000.0,
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -0,0 +1,461 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    extract_features, filter_common_municipalities, EducationData, GraphData, PopGrowthData,
+};
+
+/// Summary statistics of one municipality's category weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    /// How many of the requested categories the municipality has.
+    pub count: usize,
+}
+
+/// Summarize each municipality's weights over `categories`. Categories a
+/// municipality is missing are ignored, and municipalities with none of them
+/// are left out.
+pub fn summarize<T: GraphData>(data: &[T], categories: &[i32]) -> HashMap<String, Summary> {
+    let mut summaries = HashMap::new();
+
+    for entry in data {
+        let weights: Vec<f64> = categories
+            .iter()
+            .filter_map(|&category| entry.get_weight(category))
+            .collect();
+        if weights.is_empty() {
+            continue;
+        }
+
+        summaries.insert(
+            entry.municipality().to_string(),
+            Summary {
+                mean: weights.iter().sum::<f64>() / weights.len() as f64,
+                min: weights.iter().copied().fold(f64::INFINITY, f64::min),
+                max: weights.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                count: weights.len(),
+            },
+        );
+    }
+
+    summaries
+}
+
+/// The fraction of `categories` each municipality has a weight for, between 0
+/// and 1. Missing categories would otherwise enter the features as `0.0` and
+/// pull sparse municipalities together. With no categories every municipality
+/// is complete.
+pub fn completeness<T: GraphData>(data: &[T], categories: &[i32]) -> HashMap<String, f64> {
+    data.iter()
+        .map(|entry| {
+            let present = categories
+                .iter()
+                .filter(|&&category| entry.get_weight(category).is_some())
+                .count();
+            let fraction = if categories.is_empty() {
+                1.0
+            } else {
+                present as f64 / categories.len() as f64
+            };
+            (entry.municipality().to_string(), fraction)
+        })
+        .collect()
+}
+
+/// Pearson correlation between the education and population growth weights of
+/// every municipality present in both datasets, pairing the weights by
+/// category. Municipalities with fewer than two shared categories, or whose
+/// weights don't vary, are left out.
+pub fn correlation_by_municipality(
+    edu: &[EducationData],
+    pop: &[PopGrowthData],
+) -> HashMap<String, f64> {
+    let mut correlations = HashMap::new();
+
+    for (municipality, edu_entry, pop_entry) in filter_common_municipalities(edu, pop) {
+        let (xs, ys): (Vec<f64>, Vec<f64>) = edu_entry
+            .categories()
+            .into_iter()
+            .filter_map(|category| {
+                Some((
+                    edu_entry.get_weight(category)?,
+                    pop_entry.get_weight(category)?,
+                ))
+            })
+            .unzip();
+        if xs.len() < 2 {
+            continue;
+        }
+
+        if let Some(r) = pearson(&xs, &ys) {
+            correlations.insert(municipality, r);
+        }
+    }
+
+    correlations
+}
+
+/// The slope of the least-squares line through a record's `(year, value)`
+/// points: one point per category measured in one of `years`, or per category
+/// when `years` is empty. `None` when the points span fewer than two years.
+pub fn trend<T: GraphData>(record: &T, years: &[i32]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = record
+        .categories()
+        .into_iter()
+        .filter_map(|category| {
+            let year = record.get_year(category)?;
+            if !years.is_empty() && !years.contains(&year) {
+                return None;
+            }
+            Some((year as f64, record.get_weight(category)?))
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+
+    if variance == 0.0 {
+        return None;
+    }
+    Some(covariance / variance)
+}
+
+/// Every municipality with a [`trend`], steepest growth first and steepest
+/// decline last.
+pub fn rank_trends<T: GraphData>(data: &[T], years: &[i32]) -> Vec<(String, f64)> {
+    let mut trends: Vec<(String, f64)> = data
+        .iter()
+        .filter_map(|entry| Some((entry.municipality().to_string(), trend(entry, years)?)))
+        .collect();
+    trends.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    trends
+}
+
+/// The `n` municipalities with the largest weight of `category`, largest first.
+/// Municipalities without the category are skipped, and ties are broken by name
+/// so the ranking doesn't depend on the input order.
+pub fn top_n<T: GraphData>(data: &[T], category: i32, n: usize) -> Vec<(String, f64)> {
+    let mut ranked: Vec<(String, f64)> = data
+        .iter()
+        .filter_map(|entry| {
+            Some((
+                entry.municipality().to_string(),
+                entry.get_weight(category)?,
+            ))
+        })
+        .collect();
+    ranked.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(n);
+    ranked
+}
+
+/// Counts of the weights of `category` in `bins` equally wide bins spanning
+/// their range, as `(start, end, count)` from the lowest bin up. Every bin
+/// includes its start and the last one its end too, so each municipality with
+/// the category is counted once. When all weights are equal they share one
+/// bin; no weights at all give no bins. Zero `bins` is taken as one.
+pub fn histogram<T: GraphData>(data: &[T], category: i32, bins: usize) -> Vec<(f64, f64, usize)> {
+    let weights: Vec<f64> = data
+        .iter()
+        .filter_map(|entry| entry.get_weight(category))
+        .collect();
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    let min = weights.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = weights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let bins = if min == max { 1 } else { bins.max(1) };
+    let width = (max - min) / bins as f64;
+
+    let mut counts = vec![0; bins];
+    for weight in weights {
+        let bin = if width > 0.0 {
+            (((weight - min) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        counts[bin] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let start = min + width * i as f64;
+            let end = if i + 1 == bins {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            };
+            (start, end, count)
+        })
+        .collect()
+}
+
+/// The Pearson correlation coefficient of two equally long samples, or `None`
+/// when either sample has zero variance.
+pub fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n == 0 {
+        return None;
+    }
+
+    let mean_x = xs[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = ys[..n].iter().sum::<f64>() / n as f64;
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in xs[..n].iter().zip(&ys[..n]) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// The cosine of the angle between two equally long vectors, or `None` when
+/// either is empty or all zeros.
+pub fn cosine_similarity(a: &[f64], b: &[f64]) -> Option<f64> {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|y| y * y).sum::<f64>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a * norm_b))
+}
+
+/// The municipality names of `data`, in input order, and the symmetric matrix
+/// of Euclidean distances between their feature vectors over `categories`
+/// (see [`extract_features`]). Row and column `i` belong to name `i`.
+pub fn distance_matrix<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+) -> (Vec<String>, Vec<Vec<f64>>) {
+    let names = data
+        .iter()
+        .map(|entry| entry.municipality().to_string())
+        .collect();
+    let features = extract_features(data, categories);
+
+    // Rows are filled in parallel, each computing all of its own entries; the
+    // squared differences are the same either way round, so the matrix stays
+    // exactly symmetric
+    let distances = features
+        .par_iter()
+        .map(|a| {
+            features
+                .iter()
+                .map(|b| {
+                    a.iter()
+                        .zip(b)
+                        .map(|(x, y)| (x - y).powi(2))
+                        .sum::<f64>()
+                        .sqrt()
+                })
+                .collect()
+        })
+        .collect();
+
+    (names, distances)
+}
+
+/// The municipality closest to `name` in a matrix from [`distance_matrix`],
+/// with its distance. `None` when `name` is unknown or the only municipality.
+pub fn nearest_neighbor<'a>(
+    names: &'a [String],
+    distances: &[Vec<f64>],
+    name: &str,
+) -> Option<(&'a str, f64)> {
+    let row = names.iter().position(|n| n == name)?;
+    distances[row]
+        .iter()
+        .enumerate()
+        .filter(|&(column, _)| column != row)
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(column, &distance)| (names[column].as_str(), distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fixture;
+    use crate::{read_input, EducationData};
+
+    #[test]
+    fn completeness_is_the_fraction_of_categories_present() {
+        let mut education = Vec::new();
+        for name in ["Alpha_North", "Beta_North"] {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, 10.0);
+            entry.set(2, 2020, 10.0);
+            education.push(entry);
+        }
+        // A record with only one of the two categories, plus a third
+        let mut partial = EducationData::new("Partial");
+        partial.set(2, 2020, 5.0);
+        education.push(partial);
+
+        let fractions = completeness(&education, &[1, 2]);
+        assert_eq!(fractions.len(), 3);
+        assert_eq!(fractions["Alpha_North"], 1.0);
+        assert_eq!(fractions["Partial"], 0.5);
+        assert_eq!(completeness(&education, &[1, 2, 3, 4])["Partial"], 0.25);
+        assert_eq!(completeness(&education, &[])["Partial"], 1.0);
+    }
+
+    #[test]
+    fn correlation_pairs_the_weights_by_category() {
+        let mut education = Vec::new();
+        let mut pop_growth = Vec::new();
+        for (name, edu_weights, pop_weights) in [
+            ("Linear", [1.0, 2.0, 3.0], [2.0, 4.0, 5.0]),
+            ("Opposite", [1.0, 2.0, 3.0], [3.0, 2.0, 1.0]),
+        ] {
+            let mut edu = EducationData::new(name);
+            let mut pop = PopGrowthData::new(name);
+            for (category, (x, y)) in (1..).zip(edu_weights.into_iter().zip(pop_weights)) {
+                edu.set(category, 2020, x);
+                pop.set(category, 2020, y);
+            }
+            education.push(edu);
+            pop_growth.push(pop);
+        }
+        // Only one shared category, too few to correlate
+        let mut edu = EducationData::new("Sparse");
+        edu.set(1, 2020, 1.0);
+        edu.set(2, 2020, 2.0);
+        let mut pop = PopGrowthData::new("Sparse");
+        pop.set(2, 2020, 7.0);
+        education.push(edu);
+        pop_growth.push(pop);
+
+        let correlations = correlation_by_municipality(&education, &pop_growth);
+        assert_eq!(correlations.len(), 2);
+        // Covariance 3 over the root of variances 2 and 14/3
+        let expected = 3.0 / (2.0f64 * 14.0 / 3.0).sqrt();
+        assert!((correlations["Linear"] - expected).abs() < 1e-12);
+        assert!((correlations["Opposite"] + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn summary_covers_the_requested_categories() {
+        let mut entry = EducationData::new("Alpha");
+        for (category, weight) in [(1, 4.0), (2, 10.0), (3, 1.0), (9, 100.0)] {
+            entry.set(category, 2020, weight);
+        }
+        let empty = EducationData::new("Empty");
+
+        let summaries = summarize(&[entry, empty], &[1, 2, 3, 4]);
+        assert_eq!(
+            summaries["Alpha"],
+            Summary {
+                mean: 5.0,
+                min: 1.0,
+                max: 10.0,
+                count: 3
+            }
+        );
+        assert!(!summaries.contains_key("Empty"));
+    }
+
+    #[test]
+    fn distance_matrix_is_symmetric_with_a_zero_diagonal() {
+        let mut data = Vec::new();
+        for (name, weights) in [("A", [1.0, 2.0]), ("B", [4.0, 6.0]), ("C", [-6.0, 10.0])] {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, weights[0]);
+            entry.set(2, 2020, weights[1]);
+            data.push(entry);
+        }
+
+        let (names, distances) = distance_matrix(&data, &[1, 2]);
+        assert_eq!(names, ["A", "B", "C"]);
+        for (i, row) in distances.iter().enumerate() {
+            assert_eq!(row[i], 0.0);
+            for (j, &distance) in row.iter().enumerate() {
+                assert_eq!(distance, distances[j][i]);
+            }
+        }
+        assert_eq!(distances[0][1], 5.0);
+        assert_eq!(nearest_neighbor(&names, &distances, "A"), Some(("B", 5.0)));
+    }
+
+    #[test]
+    fn trend_of_a_linear_series_is_its_slope() {
+        // One category per year, rising by 2.5 a year
+        let mut entry = EducationData::new("Alpha");
+        for (category, year) in (1..).zip(2016..=2020) {
+            entry.set(category, year, 10.0 + 2.5 * (year - 2016) as f64);
+        }
+
+        assert!((trend(&entry, &[]).unwrap() - 2.5).abs() < 1e-12);
+        assert!((trend(&entry, &[2018, 2020]).unwrap() - 2.5).abs() < 1e-12);
+        assert_eq!(trend(&entry, &[2019]), None);
+    }
+
+    #[test]
+    fn top_n_breaks_ties_by_name_and_skips_missing_values() {
+        let mut data = Vec::new();
+        for (name, value) in [
+            ("Delta", Some(5.0)),
+            ("Bravo", Some(9.0)),
+            ("Missing", None),
+            ("Alpha", Some(5.0)),
+            ("Charlie", Some(5.0)),
+        ] {
+            let mut entry = EducationData::new(name);
+            entry.set(2, 2020, 1.0);
+            if let Some(value) = value {
+                entry.set(1, 2020, value);
+            }
+            data.push(entry);
+        }
+        let names = |data: &[EducationData], n| -> Vec<String> {
+            top_n(data, 1, n)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+
+        assert_eq!(names(&data, 3), ["Bravo", "Alpha", "Charlie"]);
+        assert_eq!(names(&data, 10), ["Bravo", "Alpha", "Charlie", "Delta"]);
+        // The input order doesn't matter
+        data.reverse();
+        assert_eq!(names(&data, 3), ["Bravo", "Alpha", "Charlie"]);
+    }
+
+    #[test]
+    fn histogram_counts_every_municipality_with_the_category() {
+        let mut education = read_input::<EducationData>(&fixture("education.json")).unwrap();
+        education.extend(read_input::<EducationData>(&fixture("education_update.json")).unwrap());
+        // Category 3 is only in the appended record, category 1 in all but that one
+        for (category, expected) in [(1, 6), (2, 7), (3, 1)] {
+            let bins = histogram(&education, category, 4);
+            let total: usize = bins.iter().map(|&(_, _, count)| count).sum();
+            assert_eq!(total, expected, "category {}", category);
+        }
+
+        let bins = histogram(&education, 1, 4);
+        assert_eq!(bins.len(), 4);
+        assert_eq!((bins[0].0, bins[3].1), (9.0, 101.0));
+        assert_eq!(histogram(&education, 3, 4).len(), 1);
+        assert!(histogram(&education, 99, 4).is_empty());
+    }
+}
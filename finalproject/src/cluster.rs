@@ -0,0 +1,806 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+
+use crate::{
+    assign_to_nearest_centroid, best_k_with_progress, cluster_by_name, cluster_outliers, dbscan,
+    distance_matrix, feature_coordinates, fmt_f64, fuzzy_cmeans, gap_statistic_with_progress,
+    geo_clusters, hierarchical_cluster, k_means_features_with_progress, k_medoids, pca_project,
+    plot_categories, plot_cluster_sizes, plot_clusters, plot_clusters_side_by_side,
+    plot_convergence, plot_map, print_ambiguous, print_cluster_report, print_iterations,
+    print_medoids, read_input_with, silhouette_score, write_clusters_csv, write_clusters_html,
+    write_distance_matrix_csv, write_report_json, AppError, ClusterPanel, ClusteringResult,
+    DatasetReport, Datasets, Delimiter, Distance, EducationData, FeatureConfig, Features,
+    GraphData, Imputation, KMeansConfig, Linkage, Output, Report,
+};
+
+/// The algorithm [`run_clustering`] clusters each dataset with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// k-means with a fixed or automatically chosen number of clusters.
+    KMeans,
+    /// k-medoids (PAM), centering each cluster on a representative municipality.
+    KMedoids,
+    /// DBSCAN, which leaves outliers unclustered as noise.
+    Dbscan,
+    /// Agglomerative clustering, cut at [`ClusterConfig::height`].
+    Hierarchical,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "k-means" => Ok(Algorithm::KMeans),
+            "k-medoids" => Ok(Algorithm::KMedoids),
+            "dbscan" => Ok(Algorithm::Dbscan),
+            "hierarchical" => Ok(Algorithm::Hierarchical),
+            _ => Err(format!(
+                "unknown algorithm '{}', expected k-means, k-medoids, dbscan or hierarchical",
+                value
+            )),
+        }
+    }
+}
+
+/// How the number of clusters is chosen when [`ClusterConfig::clusters`] is
+/// not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KMethod {
+    /// The elbow of the inertia curve.
+    Elbow,
+    /// Tibshirani's gap statistic against uniform reference data.
+    Gap,
+}
+
+impl FromStr for KMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "elbow" => Ok(KMethod::Elbow),
+            "gap" => Ok(KMethod::Gap),
+            _ => Err(format!(
+                "unknown k method '{}', expected elbow or gap",
+                value
+            )),
+        }
+    }
+}
+
+/// How [`run_clustering`] clusters the datasets and what it reports and writes.
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    /// Algorithm each dataset is clustered with.
+    pub algorithm: Algorithm,
+    /// How the categories become the clustering features.
+    pub features: FeatureConfig,
+    /// Seed, restarts, metric and iteration cap of k-means; the seed also
+    /// drives k-medoids, fuzzy c-means, the gap statistic and the geographic
+    /// clusters. Its `k` is ignored.
+    pub k_means: KMeansConfig,
+    /// Number of k-means or k-medoids clusters; chosen with `k_method` when
+    /// not set.
+    pub clusters: Option<usize>,
+    /// How the number of clusters is chosen.
+    pub k_method: KMethod,
+    /// Uniform reference datasets drawn per k by [`KMethod::Gap`].
+    pub gap_refs: usize,
+    /// With k-means, also run fuzzy c-means and print this many of the
+    /// municipalities split most evenly between clusters.
+    pub fuzzy: Option<usize>,
+    /// Fuzzifier of the fuzzy c-means run, above 1.
+    pub fuzziness: f64,
+    /// With k-means, plot the inertia after each iteration of the kept restart.
+    pub convergence: bool,
+    /// Latitude and longitude of each municipality, as read by
+    /// [`read_coordinates`](crate::read_coordinates), to plot the clusters on
+    /// maps; the maps are skipped when not set.
+    pub coordinates: Option<HashMap<String, (f64, f64)>>,
+    /// Also cluster the municipalities with `coordinates` into this many
+    /// groups by great-circle distance.
+    pub geo_clusters: Option<usize>,
+    /// Standard deviations above its cluster's mean distance to the centroid at
+    /// which a k-means member is reported as an outlier.
+    pub outlier_z: f64,
+    /// DBSCAN neighborhood radius, in feature units.
+    pub eps: f64,
+    /// Neighbors (the point included) a DBSCAN core point needs within `eps`.
+    pub min_points: usize,
+    /// Linkage of the hierarchical clustering.
+    pub linkage: Linkage,
+    /// Height at which the dendrogram is cut into clusters.
+    pub height: f64,
+    /// Education records file whose records are assigned to the nearest
+    /// education cluster; needs k-means or k-medoids.
+    pub classify: Option<String>,
+    /// Split the `classify` file on this, whatever its extension.
+    pub delimiter: Option<Delimiter>,
+    /// Also write each dataset's pairwise distance matrix to CSV.
+    pub distance_matrix: bool,
+    /// With k-means, also write the clusters and scores to `report.json`.
+    pub report: bool,
+    /// Use logarithmic axes for the category scatter plots.
+    pub log_scale: bool,
+    /// Also write interactive HTML cluster plots.
+    pub html: bool,
+    /// Show progress bars on stderr when it is a terminal.
+    pub progress: bool,
+    /// Decimal places of every number printed or written.
+    pub precision: usize,
+}
+
+impl ClusterConfig {
+    /// Cluster with `algorithm` on standardized features, choosing k by the
+    /// elbow, with the other settings at the defaults of the command line.
+    pub fn new(algorithm: Algorithm) -> Self {
+        ClusterConfig {
+            algorithm,
+            features: FeatureConfig {
+                standardize: true,
+                weights: Vec::new(),
+                imputation: Imputation::Zero,
+                drop_constant: false,
+            },
+            k_means: KMeansConfig {
+                seed: 42,
+                ..KMeansConfig::new(1)
+            },
+            clusters: None,
+            k_method: KMethod::Elbow,
+            gap_refs: 10,
+            fuzzy: None,
+            fuzziness: 2.0,
+            convergence: false,
+            coordinates: None,
+            geo_clusters: None,
+            outlier_z: 2.0,
+            eps: 1.0,
+            min_points: 4,
+            linkage: Linkage::Average,
+            height: 2.0,
+            classify: None,
+            delimiter: None,
+            distance_matrix: false,
+            report: false,
+            log_scale: false,
+            html: false,
+            progress: false,
+            precision: 4,
+        }
+    }
+}
+
+/// The cluster step: cluster each dataset with the configured algorithm and
+/// the municipalities common to both with k-means, print the clusters, and
+/// write their exports and plots through `output`.
+pub fn run_clustering(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    config: &ClusterConfig,
+    output: &Output,
+) -> Result<(), AppError> {
+    let clustering = Clustering {
+        datasets,
+        education: datasets.education.features(&config.features),
+        pop_growth: datasets.pop_growth.features(&config.features),
+        config,
+        output,
+    };
+
+    info!("clustering with {:?}", config.algorithm);
+    if config.algorithm != Algorithm::KMeans {
+        if config.fuzzy.is_some() {
+            warn!("--fuzzy only runs with k-means, skipping it");
+        }
+        if config.convergence {
+            warn!("--convergence only runs with k-means, skipping it");
+        }
+        if config.report {
+            warn!("--report only covers k-means, skipping it");
+        }
+    }
+    if matches!(
+        config.algorithm,
+        Algorithm::Dbscan | Algorithm::Hierarchical
+    ) && config.classify.is_some()
+    {
+        warn!("--classify needs cluster centers from k-means or k-medoids, skipping it");
+    }
+
+    let (education_assignments, pop_growth_assignments) = match config.algorithm {
+        Algorithm::KMeans => clustering.k_means(out)?,
+        Algorithm::KMedoids => clustering.k_medoids(out)?,
+        Algorithm::Dbscan => clustering.dbscan(out)?,
+        Algorithm::Hierarchical => clustering.hierarchical(out)?,
+    };
+    clustering.joint(out)?;
+    clustering.write(out, &education_assignments, &pop_growth_assignments)
+}
+
+// Everything the clustering of both datasets depends on
+struct Clustering<'a> {
+    datasets: &'a Datasets,
+    education: Features,
+    pop_growth: Features,
+    config: &'a ClusterConfig,
+    output: &'a Output,
+}
+
+// The clusters of the education and pop growth municipalities
+type Assignments = (HashMap<usize, Vec<String>>, HashMap<usize, Vec<String>>);
+
+impl Clustering<'_> {
+    // Cluster both datasets with k-means and print the results, their scores
+    // and any fuzzy, classify and report extras
+    fn k_means(&self, out: &mut impl Write) -> Result<Assignments, AppError> {
+        let config = self.config;
+        let precision = config.precision;
+        let education_k = self.k(&self.education.values, "Education");
+        let pop_growth_k = self.k(&self.pop_growth.values, "Pop Growth");
+        writeln!(out, "Education k: {}", education_k)?;
+        writeln!(out, "Pop Growth k: {}", pop_growth_k)?;
+        let education_clusters = self.run_k_means(
+            &self.datasets.education.municipalities(),
+            &self.education.values,
+            education_k,
+            "Education k-means",
+        )?;
+        let pop_growth_clusters = self.run_k_means(
+            &self.datasets.pop_growth.municipalities(),
+            &self.pop_growth.values,
+            pop_growth_k,
+            "Pop Growth k-means",
+        )?;
+
+        writeln!(
+            out,
+            "Education Inertia: {}",
+            fmt_f64(education_clusters.inertia, precision)
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Inertia: {}",
+            fmt_f64(pop_growth_clusters.inertia, precision)
+        )?;
+        print_iterations(out, "Education", &education_clusters)?;
+        print_iterations(out, "Pop Growth", &pop_growth_clusters)?;
+        if config.convergence {
+            self.output.plot(out, "education_convergence", |path| {
+                plot_convergence(&education_clusters.inertia_history, path)
+            })?;
+            self.output.plot(out, "pop_growth_convergence", |path| {
+                plot_convergence(&pop_growth_clusters.inertia_history, path)
+            })?;
+        }
+        let education_silhouette =
+            silhouette_score(&self.education.values, &education_clusters.labels);
+        let pop_growth_silhouette =
+            silhouette_score(&self.pop_growth.values, &pop_growth_clusters.labels);
+        writeln!(
+            out,
+            "Education Silhouette: {}",
+            fmt_f64(education_silhouette, precision)
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Silhouette: {}",
+            fmt_f64(pop_growth_silhouette, precision)
+        )?;
+        writeln!(
+            out,
+            "Education Outliers: {:?}",
+            cluster_outliers(
+                &education_clusters,
+                &self.education.values,
+                config.outlier_z
+            )
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Outliers: {:?}",
+            cluster_outliers(
+                &pop_growth_clusters,
+                &self.pop_growth.values,
+                config.outlier_z
+            )
+        )?;
+        print_cluster_report(
+            out,
+            "Education",
+            &education_clusters,
+            &self.education.names,
+            precision,
+        )?;
+        print_cluster_report(
+            out,
+            "Pop Growth",
+            &pop_growth_clusters,
+            &self.pop_growth.names,
+            precision,
+        )?;
+        if let Some(n) = config.fuzzy {
+            let seed = config.k_means.seed;
+            writeln!(out, "Education Ambiguous Municipalities:")?;
+            print_ambiguous(
+                out,
+                &self.datasets.education.municipalities(),
+                &fuzzy_cmeans(&self.education.values, education_k, config.fuzziness, seed),
+                n,
+                precision,
+            )?;
+            writeln!(out, "Pop Growth Ambiguous Municipalities:")?;
+            print_ambiguous(
+                out,
+                &self.datasets.pop_growth.municipalities(),
+                &fuzzy_cmeans(
+                    &self.pop_growth.values,
+                    pop_growth_k,
+                    config.fuzziness,
+                    seed,
+                ),
+                n,
+                precision,
+            )?;
+        }
+        self.classify(out, &education_clusters, config.k_means.metric)?;
+
+        if config.report {
+            let report = Report {
+                education: DatasetReport {
+                    clustering: education_clusters.clone(),
+                    silhouette: education_silhouette,
+                    categories: self.education.names.clone(),
+                },
+                pop_growth: DatasetReport {
+                    clustering: pop_growth_clusters.clone(),
+                    silhouette: pop_growth_silhouette,
+                    categories: self.pop_growth.names.clone(),
+                },
+                common_municipalities: self
+                    .datasets
+                    .common_municipalities()
+                    .into_iter()
+                    .map(|(municipality, _, _)| municipality)
+                    .collect(),
+            };
+            self.output.write(out, "report.json", |path| {
+                write_report_json(&report.rounded(precision), path)
+            })?;
+        }
+        Ok((
+            education_clusters.assignments,
+            pop_growth_clusters.assignments,
+        ))
+    }
+
+    // Cluster both datasets with k-medoids and print the results and their medoids
+    fn k_medoids(&self, out: &mut impl Write) -> Result<Assignments, AppError> {
+        let precision = self.config.precision;
+        let seed = self.config.k_means.seed;
+        let education_k = self.k(&self.education.values, "Education");
+        let pop_growth_k = self.k(&self.pop_growth.values, "Pop Growth");
+        writeln!(out, "Education k: {}", education_k)?;
+        writeln!(out, "Pop Growth k: {}", pop_growth_k)?;
+        let education_clusters = k_medoids(
+            &self.datasets.education.municipalities(),
+            &self.education.values,
+            education_k,
+            seed,
+        )?;
+        let pop_growth_clusters = k_medoids(
+            &self.datasets.pop_growth.municipalities(),
+            &self.pop_growth.values,
+            pop_growth_k,
+            seed,
+        )?;
+
+        writeln!(
+            out,
+            "Education Inertia: {}",
+            fmt_f64(education_clusters.inertia, precision)
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Inertia: {}",
+            fmt_f64(pop_growth_clusters.inertia, precision)
+        )?;
+        writeln!(
+            out,
+            "Education Silhouette: {}",
+            fmt_f64(
+                silhouette_score(&self.education.values, &education_clusters.labels),
+                precision
+            )
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Silhouette: {}",
+            fmt_f64(
+                silhouette_score(&self.pop_growth.values, &pop_growth_clusters.labels),
+                precision
+            )
+        )?;
+        writeln!(out, "Education Medoids:")?;
+        print_medoids(out, &education_clusters)?;
+        writeln!(out, "Pop Growth Medoids:")?;
+        print_medoids(out, &pop_growth_clusters)?;
+        print_cluster_report(
+            out,
+            "Education",
+            &education_clusters,
+            &self.education.names,
+            precision,
+        )?;
+        print_cluster_report(
+            out,
+            "Pop Growth",
+            &pop_growth_clusters,
+            &self.pop_growth.names,
+            precision,
+        )?;
+        self.classify(out, &education_clusters, Distance::Euclidean)?;
+        Ok((
+            education_clusters.assignments,
+            pop_growth_clusters.assignments,
+        ))
+    }
+
+    // Cluster both datasets with DBSCAN and print the clusters and the noise
+    fn dbscan(&self, out: &mut impl Write) -> io::Result<Assignments> {
+        let (education, pop_growth) = (&self.datasets.education, &self.datasets.pop_growth);
+        let (eps, min_points) = (self.config.eps, self.config.min_points);
+        let education_labels = dbscan(&self.education.values, eps, min_points);
+        let pop_growth_labels = dbscan(&self.pop_growth.values, eps, min_points);
+
+        writeln!(
+            out,
+            "Education Noise: {:?}",
+            education.noise(&education_labels)
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Noise: {:?}",
+            pop_growth.noise(&pop_growth_labels)
+        )?;
+        let education_assignments = education.group_by_cluster(&education_labels);
+        let pop_growth_assignments = pop_growth.group_by_cluster(&pop_growth_labels);
+        writeln!(out, "Education Clusters: {:?}", education_assignments)?;
+        writeln!(out, "Pop Growth Clusters: {:?}", pop_growth_assignments)?;
+        Ok((education_assignments, pop_growth_assignments))
+    }
+
+    // Cluster both datasets hierarchically, cut at the configured height, and
+    // print the clusters
+    fn hierarchical(&self, out: &mut impl Write) -> io::Result<Assignments> {
+        let (education, pop_growth) = (&self.datasets.education, &self.datasets.pop_growth);
+        let (linkage, height) = (self.config.linkage, self.config.height);
+        let education_labels = hierarchical_cluster(&self.education.values, linkage).cut(height);
+        let pop_growth_labels = hierarchical_cluster(&self.pop_growth.values, linkage).cut(height);
+
+        writeln!(
+            out,
+            "Education Silhouette: {}",
+            fmt_f64(
+                silhouette_score(&self.education.values, &education_labels),
+                self.config.precision
+            )
+        )?;
+        writeln!(
+            out,
+            "Pop Growth Silhouette: {}",
+            fmt_f64(
+                silhouette_score(&self.pop_growth.values, &pop_growth_labels),
+                self.config.precision
+            )
+        )?;
+        // Every municipality gets a cluster, so none are noise
+        let education_labels: Vec<Option<usize>> = education_labels.into_iter().map(Some).collect();
+        let pop_growth_labels: Vec<Option<usize>> =
+            pop_growth_labels.into_iter().map(Some).collect();
+        let education_assignments = education.group_by_cluster(&education_labels);
+        let pop_growth_assignments = pop_growth.group_by_cluster(&pop_growth_labels);
+        writeln!(out, "Education Clusters: {:?}", education_assignments)?;
+        writeln!(out, "Pop Growth Clusters: {:?}", pop_growth_assignments)?;
+        Ok((education_assignments, pop_growth_assignments))
+    }
+
+    // Cluster the municipalities present in both datasets on both at once
+    fn joint(&self, out: &mut impl Write) -> io::Result<()> {
+        let precision = self.config.precision;
+        let joint = self.datasets.joint_features(
+            &self.education,
+            &self.pop_growth,
+            self.config.features.standardize,
+        );
+        let joint_k = self.k(&joint.values, "Joint");
+        writeln!(out, "Joint k: {}", joint_k)?;
+        match self.run_k_means(
+            &joint.municipalities,
+            &joint.values,
+            joint_k,
+            "Joint k-means",
+        ) {
+            Ok(joint_clusters) => {
+                print_cluster_report(out, "Joint", &joint_clusters, &joint.names, precision)?;
+                writeln!(
+                    out,
+                    "Joint Silhouette: {}",
+                    fmt_f64(
+                        silhouette_score(&joint.values, &joint_clusters.labels),
+                        precision
+                    )
+                )?;
+                self.output.write(out, "joint_clusters.csv", |path| {
+                    write_clusters_csv(&joint_clusters.assignments, path)
+                })?;
+            }
+            // Few municipalities may be common to both files, so this isn't fatal
+            Err(err) => warn!("skipping joint clustering: {}", err),
+        }
+        Ok(())
+    }
+
+    // Export the clusters and distances, plot the clusters on their principal
+    // components, categories and map, and cluster by location if asked
+    fn write(
+        &self,
+        out: &mut impl Write,
+        education_assignments: &HashMap<usize, Vec<String>>,
+        pop_growth_assignments: &HashMap<usize, Vec<String>>,
+    ) -> Result<(), AppError> {
+        let (education, pop_growth) = (&self.datasets.education, &self.datasets.pop_growth);
+        let (config, output) = (self.config, self.output);
+        let precision = config.precision;
+
+        // Export cluster assignments
+        output.write(out, "education_clusters.csv", |path| {
+            write_clusters_csv(education_assignments, path)
+        })?;
+        output.write(out, "pop_growth_clusters.csv", |path| {
+            write_clusters_csv(pop_growth_assignments, path)
+        })?;
+
+        // Plot how many municipalities landed in each cluster
+        output.plot(out, "education_cluster_sizes", |path| {
+            plot_cluster_sizes(education_assignments, path)
+        })?;
+        output.plot(out, "pop_growth_cluster_sizes", |path| {
+            plot_cluster_sizes(pop_growth_assignments, path)
+        })?;
+
+        // Export pairwise distances between municipalities
+        if config.distance_matrix {
+            let (names, distances) =
+                distance_matrix(&education.records, &self.education.categories);
+            output.write(out, "education_distances.csv", |path| {
+                write_distance_matrix_csv(&names, &distances, precision, path)
+            })?;
+            let (names, distances) =
+                distance_matrix(&pop_growth.records, &self.pop_growth.categories);
+            output.write(out, "pop_growth_distances.csv", |path| {
+                write_distance_matrix_csv(&names, &distances, precision, path)
+            })?;
+        }
+
+        // Plot clusters on the first two principal components of their features
+        let education_pca =
+            feature_coordinates(&education.records, &pca_project(&self.education.values, 2));
+        let pop_growth_pca = feature_coordinates(
+            &pop_growth.records,
+            &pca_project(&self.pop_growth.values, 2),
+        );
+        output.plot(out, "education_clusters", |path| {
+            plot_clusters(education_assignments, &education_pca, "PC1", "PC2", path)
+        })?;
+        output.plot(out, "pop_growth_clusters", |path| {
+            plot_clusters(pop_growth_assignments, &pop_growth_pca, "PC1", "PC2", path)
+        })?;
+        output.plot(out, "clusters_side_by_side", |path| {
+            plot_clusters_side_by_side(
+                &[
+                    ClusterPanel {
+                        title: "Education",
+                        clusters: education_assignments,
+                        coordinates: &education_pca,
+                    },
+                    ClusterPanel {
+                        title: "Pop Growth",
+                        clusters: pop_growth_assignments,
+                        coordinates: &pop_growth_pca,
+                    },
+                ],
+                "PC1",
+                "PC2",
+                path,
+            )
+        })?;
+        if config.html {
+            output.write(out, "education_clusters.html", |path| {
+                write_clusters_html(
+                    &education.records,
+                    education_assignments,
+                    &education_pca,
+                    &education.key,
+                    precision,
+                    path,
+                )
+            })?;
+            output.write(out, "pop_growth_clusters.html", |path| {
+                write_clusters_html(
+                    &pop_growth.records,
+                    pop_growth_assignments,
+                    &pop_growth_pca,
+                    &pop_growth.key,
+                    precision,
+                    path,
+                )
+            })?;
+        }
+
+        // Plot the first two categories of each dataset against each other
+        if let [cat_x, cat_y, ..] = self.education.categories[..] {
+            output.plot(out, "education_categories", |path| {
+                plot_categories(
+                    &education.records,
+                    cat_x,
+                    cat_y,
+                    &cluster_by_name(education_assignments),
+                    &education.key,
+                    config.log_scale,
+                    path,
+                )
+            })?;
+        }
+        if let [cat_x, cat_y, ..] = self.pop_growth.categories[..] {
+            output.plot(out, "pop_growth_categories", |path| {
+                plot_categories(
+                    &pop_growth.records,
+                    cat_x,
+                    cat_y,
+                    &cluster_by_name(pop_growth_assignments),
+                    &pop_growth.key,
+                    config.log_scale,
+                    path,
+                )
+            })?;
+        }
+
+        // Plot clusters by location on a map, if there are coordinates to place them
+        let Some(coordinates) = &config.coordinates else {
+            if config.geo_clusters.is_some() {
+                warn!("--geo-clusters needs --coordinates, skipping it");
+            }
+            return Ok(());
+        };
+        output.plot(out, "education_map", |path| {
+            plot_map(coordinates, &cluster_by_name(education_assignments), path)
+        })?;
+        output.plot(out, "pop_growth_map", |path| {
+            plot_map(coordinates, &cluster_by_name(pop_growth_assignments), path)
+        })?;
+
+        // Group the municipalities by location alone if requested
+        if let Some(k) = config.geo_clusters {
+            let (located, points) = self.datasets.located(coordinates);
+            let geo = geo_clusters(&located, &points, k, config.k_means.seed)?;
+            writeln!(
+                out,
+                "Geographic Inertia (km²): {}",
+                fmt_f64(geo.inertia, precision)
+            )?;
+            writeln!(out, "Geographic Medoids:")?;
+            print_medoids(out, &geo)?;
+            output.write(out, "geo_clusters.csv", |path| {
+                write_clusters_csv(&geo.assignments, path)
+            })?;
+            output.plot(out, "geo_map", |path| {
+                plot_map(coordinates, &cluster_by_name(&geo.assignments), path)
+            })?;
+        }
+        Ok(())
+    }
+
+    // The number of clusters: the configured one, or chosen by the k method
+    fn k(&self, features: &[Vec<f64>], dataset: &str) -> usize {
+        self.config
+            .clusters
+            .unwrap_or_else(|| choose_k(features, self.config, dataset))
+    }
+
+    // Run k-means with `k` clusters under the progress bar `message`
+    fn run_k_means(
+        &self,
+        municipalities: &[String],
+        features: &[Vec<f64>],
+        k: usize,
+        message: &str,
+    ) -> Result<ClusteringResult, AppError> {
+        let config = KMeansConfig {
+            k,
+            ..self.config.k_means
+        };
+        with_progress(self.config.progress, config.n_init.max(1), message, |bar| {
+            k_means_features_with_progress(municipalities, features, &config, |_| bar.inc(1))
+        })
+    }
+
+    // Print the education cluster of `result` each record of the classify file
+    // would join, comparing its features to the centroids under `metric`
+    fn classify(
+        &self,
+        out: &mut impl Write,
+        result: &ClusteringResult,
+        metric: Distance,
+    ) -> Result<(), AppError> {
+        let Some(path) = &self.config.classify else {
+            return Ok(());
+        };
+        writeln!(out, "Classified Municipalities:")?;
+        let records = read_input_with::<EducationData>(path, self.config.delimiter)?;
+        let features = self.datasets.education.classification_features(
+            &records,
+            &self.education,
+            &self.config.features,
+        );
+        for (record, point) in records.iter().zip(&features) {
+            writeln!(
+                out,
+                "  {}: cluster {}",
+                record.municipality(),
+                assign_to_nearest_centroid(&result.centroids, point, metric)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Run `work` with a progress bar over `len` steps on stderr. The bar is only
+// drawn when `enabled` and stderr is a terminal, so redirected output and logs
+// stay clean.
+fn with_progress<R>(
+    enabled: bool,
+    len: usize,
+    message: &str,
+    work: impl FnOnce(&ProgressBar) -> R,
+) -> R {
+    let bar = if enabled && io::stderr().is_terminal() {
+        let bar = ProgressBar::new(len as u64).with_message(message.to_string());
+        bar.set_style(
+            ProgressStyle::with_template("{msg:20} [{bar:40}] {pos}/{len}")
+                .expect("progress bar template is valid"),
+        );
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+    let result = work(&bar);
+    bar.finish_and_clear();
+    result
+}
+
+// Choose k with the configured method over 1..=10, or fewer when there are
+// fewer points
+fn choose_k(features: &[Vec<f64>], config: &ClusterConfig, dataset: &str) -> usize {
+    let k_range = 1..=features.len().min(10);
+    let len = k_range.clone().count();
+    match config.k_method {
+        KMethod::Elbow => {
+            with_progress(config.progress, len, &format!("{} elbow", dataset), |bar| {
+                best_k_with_progress(features, k_range, |_| bar.inc(1))
+            })
+        }
+        KMethod::Gap => with_progress(config.progress, len, &format!("{} gap", dataset), |bar| {
+            gap_statistic_with_progress(
+                features,
+                k_range,
+                config.gap_refs,
+                config.k_means.seed,
+                |_| bar.inc(1),
+            )
+        }),
+    }
+}
@@ -0,0 +1,1983 @@
+use log::debug;
+use nalgebra::DMatrix;
+use petgraph::unionfind::UnionFind;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::{filter_common_municipalities, AppError, EducationData, GraphData, PopGrowthData};
+
+// Seed used for every k-means run of the elbow sweep
+const ELBOW_SEED: u64 = 0;
+
+/// The outcome of a k-means or k-medoids run, with the clusters numbered as
+/// [`canonicalize_clusters`] does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusteringResult {
+    /// Municipality names per cluster index.
+    pub assignments: HashMap<usize, Vec<String>>,
+    /// The municipality of each input record, in input order.
+    pub municipalities: Vec<String>,
+    /// The cluster index of each input record, in input order.
+    pub labels: Vec<usize>,
+    /// One centroid per cluster, in the (possibly standardized) feature space.
+    pub centroids: Vec<Vec<f64>>,
+    /// Within-cluster sum of squared distances to the centroids, under the
+    /// run's metric.
+    pub inertia: f64,
+    /// Lloyd iterations the kept restart took, counting the last one, in
+    /// which no assignment changed.
+    pub iterations: usize,
+    /// Whether that restart converged, rather than being cut off at
+    /// `max_iterations`.
+    pub converged: bool,
+    /// For k-means, the inertia after each Lloyd iteration of that restart, one
+    /// entry per iteration and ending at `inertia`; empty for k-medoids.
+    #[serde(default)]
+    pub inertia_history: Vec<f64>,
+    /// For k-medoids, the input index of each cluster's medoid; empty for k-means.
+    #[serde(default)]
+    pub medoids: Vec<usize>,
+}
+
+/// The cluster index of each municipality, keyed by name; the inverse of
+/// [`ClusteringResult::assignments`].
+pub fn cluster_by_name(clusters: &HashMap<usize, Vec<String>>) -> HashMap<String, usize> {
+    clusters
+        .iter()
+        .flat_map(|(&cluster, members)| members.iter().map(move |m| (m.clone(), cluster)))
+        .collect()
+}
+
+/// Renumber the clusters of `result` in order of their alphabetically first
+/// member, so the same grouping gets the same numbers, and so the same plot
+/// colors, whatever the input order or the RNG made of the raw labels. Empty
+/// clusters go last.
+pub fn canonicalize_clusters(result: &mut ClusteringResult) {
+    let mut order: Vec<usize> = (0..result.centroids.len()).collect();
+    order.sort_by_key(|cluster| {
+        let first = result
+            .assignments
+            .get(cluster)
+            .and_then(|members| members.iter().min());
+        (first.is_none(), first.cloned(), *cluster)
+    });
+    let mut renumbered = vec![0; order.len()];
+    for (new, &old) in order.iter().enumerate() {
+        renumbered[old] = new;
+    }
+
+    result.assignments = result
+        .assignments
+        .drain()
+        .map(|(cluster, members)| (renumbered[cluster], members))
+        .collect();
+    for label in &mut result.labels {
+        *label = renumbered[*label];
+    }
+    result.centroids = order
+        .iter()
+        .map(|&old| result.centroids[old].clone())
+        .collect();
+    if !result.medoids.is_empty() {
+        result.medoids = order.iter().map(|&old| result.medoids[old]).collect();
+    }
+}
+
+/// Like [`canonicalize_clusters`], for bare cluster assignments such as those
+/// grouped from DBSCAN or hierarchical labels. The clusters are numbered from 0.
+pub fn canonicalize_assignments(
+    clusters: HashMap<usize, Vec<String>>,
+) -> HashMap<usize, Vec<String>> {
+    let mut groups: Vec<Vec<String>> = clusters.into_values().collect();
+    groups.sort_by_cached_key(|members| members.iter().min().cloned());
+    groups.into_iter().enumerate().collect()
+}
+
+/// How far apart two feature vectors are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distance {
+    /// Straight-line distance.
+    Euclidean,
+    /// Sum of the absolute differences per dimension, which lets a single
+    /// extreme dimension weigh less than under Euclidean distance.
+    Manhattan,
+    /// Largest absolute difference in any one dimension.
+    Chebyshev,
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "euclidean" => Ok(Distance::Euclidean),
+            "manhattan" => Ok(Distance::Manhattan),
+            "chebyshev" => Ok(Distance::Chebyshev),
+            _ => Err(format!(
+                "unknown metric '{}', expected euclidean, manhattan or chebyshev",
+                value
+            )),
+        }
+    }
+}
+
+/// The distance between two equally long feature vectors under `metric`.
+pub fn dist(a: &[f64], b: &[f64], metric: Distance) -> f64 {
+    let differences = a.iter().zip(b).map(|(x, y)| (x - y).abs());
+    match metric {
+        Distance::Euclidean => squared_distance(a, b).sqrt(),
+        Distance::Manhattan => differences.sum(),
+        Distance::Chebyshev => differences.fold(0.0, f64::max),
+    }
+}
+
+/// The great-circle distance in kilometers between two `(latitude, longitude)`
+/// points given in degrees, on a sphere of the Earth's mean radius.
+pub fn haversine(a: (f64, f64), b: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0088;
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+/// Settings of a k-means run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KMeansConfig {
+    /// Number of clusters.
+    pub k: usize,
+    /// Seed of the first restart's RNG; restart `i` uses `seed + i`.
+    pub seed: u64,
+    /// Number of restarts, of which the one with the lowest inertia is kept.
+    pub n_init: usize,
+    /// How points are matched to centroids. Under Manhattan distance each
+    /// centroid moves to the median of its members rather than their mean.
+    pub metric: Distance,
+    /// Cap on the Lloyd iterations of each restart.
+    pub max_iterations: usize,
+}
+
+impl KMeansConfig {
+    /// `k` clusters, seed 0, 10 restarts, Euclidean distance and at most
+    /// 100 iterations per restart.
+    pub fn new(k: usize) -> Self {
+        KMeansConfig {
+            k,
+            seed: 0,
+            n_init: 10,
+            metric: Distance::Euclidean,
+            max_iterations: 100,
+        }
+    }
+}
+
+/// Cluster municipalities on one feature dimension per entry of `categories`.
+/// A municipality missing one of the categories gets `0.0` in that dimension.
+/// With `normalize` set, every dimension is z-scored first so that categories
+/// measured on large scales don't dominate the distances. The best of
+/// `config.n_init` restarts is kept (see [`k_means_features`]), and the initial
+/// centroids are drawn from RNGs seeded from `config.seed`, so equal inputs give
+/// equal clusters. After normalizing, dimension `i` is scaled by `weights[i]`
+/// (see [`weight_features`]) to make some categories count more than others;
+/// empty `weights` leave every dimension as is.
+pub fn k_means_clustering<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+    weights: &[f64],
+    normalize: bool,
+    config: &KMeansConfig,
+) -> Result<ClusteringResult, AppError> {
+    k_means_clustering_with_progress(data, categories, weights, normalize, config, |_| {})
+}
+
+/// Like [`k_means_clustering`], calling `on_restart` with the number of
+/// finished restarts after each one, e.g. to advance a progress bar.
+pub fn k_means_clustering_with_progress<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+    weights: &[f64],
+    normalize: bool,
+    config: &KMeansConfig,
+    on_restart: impl FnMut(usize),
+) -> Result<ClusteringResult, AppError> {
+    // Extract features for k-means clustering
+    let mut features = extract_features(data, categories);
+    if normalize {
+        standardize(&mut features);
+    }
+    weight_features(&mut features, weights);
+
+    let municipalities: Vec<String> = data
+        .iter()
+        .map(|entry| entry.municipality().to_string())
+        .collect();
+    k_means_features_with_progress(&municipalities, &features, config, on_restart)
+}
+
+/// Run k-means directly on prepared feature vectors, one per entry of
+/// `municipalities` and in the same order. The algorithm is restarted
+/// `config.n_init` times (at least once), run `i` drawing its initial centroids
+/// from an RNG seeded with `config.seed + i`, and the run with the lowest
+/// inertia is kept. Fails when `config.k` is zero or larger than the number of
+/// feature vectors.
+pub fn k_means_features(
+    municipalities: &[String],
+    features: &[Vec<f64>],
+    config: &KMeansConfig,
+) -> Result<ClusteringResult, AppError> {
+    k_means_features_with_progress(municipalities, features, config, |_| {})
+}
+
+/// Like [`k_means_features`], calling `on_restart` with the number of finished
+/// restarts after each one.
+pub fn k_means_features_with_progress(
+    municipalities: &[String],
+    features: &[Vec<f64>],
+    config: &KMeansConfig,
+    mut on_restart: impl FnMut(usize),
+) -> Result<ClusteringResult, AppError> {
+    let KMeansConfig {
+        k,
+        seed,
+        n_init,
+        metric,
+        max_iterations,
+    } = *config;
+    if k == 0 || k > features.len() {
+        return Err(AppError::ClusterCount {
+            k,
+            records: features.len(),
+        });
+    }
+
+    // Perform k-means clustering, keeping the best restart
+    let mut best: Option<(LloydRun, f64)> = None;
+    for restart in 0..n_init.max(1) {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(restart as u64));
+        let run = lloyd(features, k, metric, max_iterations, &mut rng);
+        let inertia = metric_inertia(features, &run.assignments, &run.centroids, metric);
+        if best
+            .as_ref()
+            .is_none_or(|&(_, best_inertia)| inertia < best_inertia)
+        {
+            best = Some((run, inertia));
+        }
+        on_restart(restart + 1);
+    }
+    let (run, inertia) = best.expect("k-means runs at least once");
+
+    // Organize results into HashMap
+    let mut result_clusters: HashMap<usize, Vec<String>> = HashMap::new();
+    for (i, cluster_idx) in run.assignments.iter().enumerate() {
+        result_clusters
+            .entry(*cluster_idx)
+            .or_default()
+            .push(municipalities[i].clone());
+    }
+
+    let mut result = ClusteringResult {
+        assignments: result_clusters,
+        municipalities: municipalities.to_vec(),
+        labels: run.assignments,
+        centroids: run.centroids,
+        inertia,
+        iterations: run.iterations,
+        converged: run.converged,
+        inertia_history: run.inertia_history,
+        medoids: Vec::new(),
+    };
+    canonicalize_clusters(&mut result);
+    Ok(result)
+}
+
+/// Partitioning around medoids (PAM): like [`k_means_features`], but each
+/// cluster is centered on one of its members, its medoid, so an outlier can't
+/// drag the center away the way it drags a mean. Starting from `k` medoids
+/// drawn with an RNG seeded with `seed`, the swap of a medoid for a non-medoid
+/// that lowers the total Euclidean distance of the points to their nearest
+/// medoid the most is made, until no swap lowers it. The result's `medoids`
+/// index into `features` and `municipalities`, its centroids are the medoids'
+/// feature vectors and its inertia is measured as for k-means. Fails when `k`
+/// is zero or larger than the number of feature vectors.
+pub fn k_medoids(
+    municipalities: &[String],
+    features: &[Vec<f64>],
+    k: usize,
+    seed: u64,
+) -> Result<ClusteringResult, AppError> {
+    if k == 0 || k > features.len() {
+        return Err(AppError::ClusterCount {
+            k,
+            records: features.len(),
+        });
+    }
+
+    let distances: Vec<Vec<f64>> = features
+        .par_iter()
+        .map(|a| {
+            features
+                .iter()
+                .map(|b| dist(a, b, Distance::Euclidean))
+                .collect()
+        })
+        .collect();
+    let run = pam(&distances, k, seed);
+    let centroids: Vec<Vec<f64>> = run.medoids.iter().map(|&m| features[m].clone()).collect();
+    let inertia = inertia(features, &run.labels, &centroids);
+    Ok(medoid_result(municipalities, centroids, run, inertia))
+}
+
+/// Cluster municipalities by where they are: [`k_medoids`] on the great-circle
+/// distances between their `(latitude, longitude)` `coordinates`, in the order
+/// of `municipalities`. The centroids are the medoids' coordinates and the
+/// inertia is the sum of squared distances to them in km². Fails when `k` is
+/// zero or larger than the number of municipalities.
+pub fn geo_clusters(
+    municipalities: &[String],
+    coordinates: &[(f64, f64)],
+    k: usize,
+    seed: u64,
+) -> Result<ClusteringResult, AppError> {
+    if k == 0 || k > coordinates.len() {
+        return Err(AppError::ClusterCount {
+            k,
+            records: coordinates.len(),
+        });
+    }
+
+    let distances: Vec<Vec<f64>> = coordinates
+        .par_iter()
+        .map(|&a| coordinates.iter().map(|&b| haversine(a, b)).collect())
+        .collect();
+    let run = pam(&distances, k, seed);
+    let centroids: Vec<Vec<f64>> = run
+        .medoids
+        .iter()
+        .map(|&m| vec![coordinates[m].0, coordinates[m].1])
+        .collect();
+    let inertia = run
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(i, &cluster)| distances[i][run.medoids[cluster]].powi(2))
+        .sum();
+    Ok(medoid_result(municipalities, centroids, run, inertia))
+}
+
+// The medoids PAM settled on, with every point's cluster
+struct PamRun {
+    medoids: Vec<usize>,
+    labels: Vec<usize>,
+    iterations: usize,
+}
+
+// PAM over a table of pairwise distances, see `k_medoids`; 1 <= k <= n
+fn pam(distances: &[Vec<f64>], k: usize, seed: u64) -> PamRun {
+    let n = distances.len();
+    let cost = |medoids: &[usize]| -> f64 {
+        (0..n)
+            .map(|i| {
+                medoids
+                    .iter()
+                    .map(|&m| distances[i][m])
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .sum()
+    };
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut medoids = rand::seq::index::sample(&mut rng, n, k).into_vec();
+    let mut current = cost(&medoids);
+    let mut iterations = 0;
+    loop {
+        iterations += 1;
+        let mut best_swap = None;
+        for slot in 0..k {
+            for candidate in 0..n {
+                if medoids.contains(&candidate) {
+                    continue;
+                }
+                let mut swapped = medoids.clone();
+                swapped[slot] = candidate;
+                let swapped_cost = cost(&swapped);
+                if swapped_cost < current {
+                    current = swapped_cost;
+                    best_swap = Some((slot, candidate));
+                }
+            }
+        }
+        let Some((slot, candidate)) = best_swap else {
+            break;
+        };
+        medoids[slot] = candidate;
+    }
+
+    // Medoids keep their own cluster even when they coincide with another one
+    let labels: Vec<usize> = (0..n)
+        .map(|i| {
+            medoids.iter().position(|&m| m == i).unwrap_or_else(|| {
+                (0..k)
+                    .min_by(|&a, &b| distances[i][medoids[a]].total_cmp(&distances[i][medoids[b]]))
+                    .expect("k is at least 1")
+            })
+        })
+        .collect();
+
+    PamRun {
+        medoids,
+        labels,
+        iterations,
+    }
+}
+
+// The canonicalized result of a PAM run, centered on `centroids`
+fn medoid_result(
+    municipalities: &[String],
+    centroids: Vec<Vec<f64>>,
+    run: PamRun,
+    inertia: f64,
+) -> ClusteringResult {
+    let mut assignments: HashMap<usize, Vec<String>> = HashMap::new();
+    for (i, &cluster) in run.labels.iter().enumerate() {
+        assignments
+            .entry(cluster)
+            .or_default()
+            .push(municipalities[i].clone());
+    }
+
+    let mut result = ClusteringResult {
+        assignments,
+        municipalities: municipalities.to_vec(),
+        labels: run.labels,
+        centroids,
+        inertia,
+        iterations: run.iterations,
+        converged: true,
+        inertia_history: Vec::new(),
+        medoids: run.medoids,
+    };
+    canonicalize_clusters(&mut result);
+    result
+}
+
+/// The index of the centroid closest to `features` under `metric`: the cluster
+/// a new municipality would join, without refitting. `features` must be
+/// prepared like the ones the clustering ran on, and `metric` should be the
+/// one it ran with ([`KMeansConfig::metric`], or Euclidean for k-medoids) so
+/// the point lands where the clustering would have put it. Ties go to the
+/// lower index, and an empty `centroids` gives 0.
+pub fn assign_to_nearest_centroid(
+    centroids: &[Vec<f64>],
+    features: &[f64],
+    metric: Distance,
+) -> usize {
+    nearest_centroid(centroids, features, metric)
+}
+
+/// Municipalities lying unusually far from their cluster's centroid: those whose
+/// distance to it is more than `z_threshold` standard deviations above the mean
+/// distance of the cluster's members. `features` must be the feature vectors the
+/// clustering ran on, in input order. Sorted by name.
+pub fn cluster_outliers(
+    result: &ClusteringResult,
+    features: &[Vec<f64>],
+    z_threshold: f64,
+) -> Vec<String> {
+    let distances: Vec<f64> = features
+        .iter()
+        .zip(&result.labels)
+        .map(|(point, &cluster)| squared_distance(point, &result.centroids[cluster]).sqrt())
+        .collect();
+
+    let mut outliers = Vec::new();
+    for cluster in 0..result.centroids.len() {
+        let members: Vec<usize> = (0..distances.len())
+            .filter(|&i| result.labels[i] == cluster)
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+
+        let n = members.len() as f64;
+        let mean = members.iter().map(|&i| distances[i]).sum::<f64>() / n;
+        let std = (members
+            .iter()
+            .map(|&i| (distances[i] - mean).powi(2))
+            .sum::<f64>()
+            / n)
+            .sqrt();
+        if std == 0.0 {
+            continue;
+        }
+
+        for &i in &members {
+            if (distances[i] - mean) / std > z_threshold {
+                outliers.push(result.municipalities[i].clone());
+            }
+        }
+    }
+
+    outliers.sort_unstable();
+    outliers
+}
+
+/// Density-based clustering (DBSCAN). A point with at least `min_points`
+/// points (itself included) within distance `eps` is a core point; clusters
+/// grow from core points through their neighbors. Returns the cluster index of
+/// every point, or `None` for noise that is not reachable from any core point.
+pub fn dbscan(features: &[Vec<f64>], eps: f64, min_points: usize) -> Vec<Option<usize>> {
+    let eps_squared = eps * eps;
+    let neighbors = |i: usize| -> Vec<usize> {
+        (0..features.len())
+            .filter(|&j| squared_distance(&features[i], &features[j]) <= eps_squared)
+            .collect()
+    };
+
+    let mut labels = vec![None; features.len()];
+    let mut visited = vec![false; features.len()];
+    let mut cluster = 0;
+    for i in 0..features.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let mut queue = neighbors(i);
+        // Noise for now; a later cluster may still claim it as a border point
+        if queue.len() < min_points {
+            continue;
+        }
+
+        labels[i] = Some(cluster);
+        while let Some(j) = queue.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(cluster);
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+            let reachable = neighbors(j);
+            if reachable.len() >= min_points {
+                queue.extend(reachable);
+            }
+        }
+        cluster += 1;
+    }
+
+    labels
+}
+
+/// Sum of squared Euclidean distances from each point to its assigned centroid.
+pub fn inertia(features: &[Vec<f64>], assignments: &[usize], centroids: &[Vec<f64>]) -> f64 {
+    metric_inertia(features, assignments, centroids, Distance::Euclidean)
+}
+
+// Like `inertia`, with the distances measured under `metric`
+fn metric_inertia(
+    features: &[Vec<f64>],
+    assignments: &[usize],
+    centroids: &[Vec<f64>],
+    metric: Distance,
+) -> f64 {
+    features
+        .iter()
+        .zip(assignments)
+        .map(|(point, &cluster)| squared_dist(point, &centroids[cluster], metric))
+        .sum()
+}
+
+/// The mean silhouette coefficient over all points, using Euclidean distance.
+/// For each point `a` is its mean distance to the rest of its cluster and `b`
+/// its mean distance to the nearest other cluster; its coefficient is
+/// `(b - a) / max(a, b)`, or `0.0` when it is alone in its cluster. The result
+/// lies in `[-1, 1]`, higher meaning better separated clusters.
+pub fn silhouette_score(features: &[Vec<f64>], assignments: &[usize]) -> f64 {
+    let n = features.len().min(assignments.len());
+    if n == 0 {
+        return 0.0;
+    }
+    let cluster_count = assignments[..n].iter().max().map_or(0, |&c| c + 1);
+
+    let mut total = 0.0;
+    for i in 0..n {
+        // Sum and count of distances from point i to each cluster
+        let mut sums = vec![0.0; cluster_count];
+        let mut counts = vec![0usize; cluster_count];
+        for j in 0..n {
+            if i != j {
+                sums[assignments[j]] += squared_distance(&features[i], &features[j]).sqrt();
+                counts[assignments[j]] += 1;
+            }
+        }
+
+        let own = assignments[i];
+        if counts[own] == 0 {
+            continue;
+        }
+        let a = sums[own] / counts[own] as f64;
+        let b = (0..cluster_count)
+            .filter(|&c| c != own && counts[c] > 0)
+            .map(|c| sums[c] / counts[c] as f64)
+            .fold(f64::INFINITY, f64::min);
+        if b.is_finite() && a.max(b) > 0.0 {
+            total += (b - a) / a.max(b);
+        }
+    }
+
+    total / n as f64
+}
+
+/// Soft clustering with fuzzy c-means: rather than one cluster per feature
+/// vector, the membership of each vector in each of `k` clusters, one row per
+/// vector and one column per cluster, every row summing to 1. The fuzzifier `m`
+/// sets how soft memberships are, from nearly hard just above 1 to ever more
+/// even as it grows; values not above 1 are taken as 1.01. Centroids start
+/// from k-means++ seeding with an RNG seeded with `seed`, and memberships are
+/// updated until none moves by more than 1e-6 or for at most 300 rounds. `k` is
+/// capped at the number of vectors, and zero `k` gives empty rows.
+pub fn fuzzy_cmeans(features: &[Vec<f64>], k: usize, m: f64, seed: u64) -> Vec<Vec<f64>> {
+    let k = k.min(features.len());
+    if k == 0 {
+        return vec![Vec::new(); features.len()];
+    }
+    let m = if m > 1.0 { m } else { 1.01 };
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut centroids = k_means_plus_plus(features, k, Distance::Euclidean, &mut rng);
+    let mut memberships = vec![vec![0.0; k]; features.len()];
+    for round in 1..=FUZZY_MAX_ROUNDS {
+        let updated: Vec<Vec<f64>> = features
+            .par_iter()
+            .map(|point| fuzzy_memberships(&centroids, point, m))
+            .collect();
+        let change = memberships
+            .iter()
+            .zip(&updated)
+            .flat_map(|(old, new)| old.iter().zip(new).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        memberships = updated;
+        if change < FUZZY_TOLERANCE {
+            debug!(
+                "fuzzy c-means with k = {} converged after {} rounds",
+                k, round
+            );
+            break;
+        }
+
+        // Move each centroid to the mean of all points weighted by membership^m
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let weights: Vec<f64> = memberships.iter().map(|row| row[cluster].powf(m)).collect();
+            let total: f64 = weights.iter().sum();
+            if total <= 0.0 {
+                continue;
+            }
+            for (d, value) in centroid.iter_mut().enumerate() {
+                *value = features
+                    .iter()
+                    .zip(&weights)
+                    .map(|(point, weight)| point[d] * weight)
+                    .sum::<f64>()
+                    / total;
+            }
+        }
+    }
+
+    memberships
+}
+
+// Cap and convergence threshold of the membership updates of fuzzy c-means
+const FUZZY_MAX_ROUNDS: usize = 300;
+const FUZZY_TOLERANCE: f64 = 1e-6;
+
+// Memberships of `point` given `centroids`: inversely proportional to the
+// distance to each raised to 2 / (m - 1). A point on one or more centroids
+// belongs to those alone, evenly.
+fn fuzzy_memberships(centroids: &[Vec<f64>], point: &[f64], m: f64) -> Vec<f64> {
+    let distances: Vec<f64> = centroids
+        .iter()
+        .map(|centroid| dist(point, centroid, Distance::Euclidean))
+        .collect();
+    let on_centroid = distances.iter().filter(|&&d| d == 0.0).count();
+    if on_centroid > 0 {
+        return distances
+            .iter()
+            .map(|&d| {
+                if d == 0.0 {
+                    1.0 / on_centroid as f64
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+    }
+
+    let exponent = 2.0 / (m - 1.0);
+    let inverse: Vec<f64> = distances.iter().map(|d| d.powf(-exponent)).collect();
+    let total: f64 = inverse.iter().sum();
+    inverse.iter().map(|value| value / total).collect()
+}
+
+/// The `n` municipalities whose rows of `memberships` (from [`fuzzy_cmeans`], in
+/// the order of `municipalities`) have the highest Shannon entropy in nats,
+/// i.e. that are split most evenly between clusters, most ambiguous first.
+/// Ties are broken by name.
+pub fn most_ambiguous(
+    municipalities: &[String],
+    memberships: &[Vec<f64>],
+    n: usize,
+) -> Vec<(String, f64)> {
+    let mut ranked: Vec<(String, f64)> = municipalities
+        .iter()
+        .zip(memberships)
+        .map(|(municipality, row)| {
+            let entropy = -row
+                .iter()
+                .filter(|&&p| p > 0.0)
+                .map(|&p| p * p.ln())
+                .sum::<f64>();
+            (municipality.clone(), entropy.max(0.0))
+        })
+        .collect();
+    ranked.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(n);
+    ranked
+}
+
+/// Pick `k` with the elbow method: run k-means for every `k` in `k_range` and
+/// return the one whose point on the (normalized) inertia curve lies furthest
+/// from the straight line joining the first and last points.
+pub fn best_k(features: &[Vec<f64>], k_range: RangeInclusive<usize>) -> usize {
+    best_k_with_progress(features, k_range, |_| {})
+}
+
+/// Like [`best_k`], calling `on_k` with each `k` of the sweep once it has run.
+pub fn best_k_with_progress(
+    features: &[Vec<f64>],
+    k_range: RangeInclusive<usize>,
+    mut on_k: impl FnMut(usize),
+) -> usize {
+    let ks: Vec<usize> = k_range.collect();
+    if ks.len() < 3 {
+        return ks.first().copied().unwrap_or(1);
+    }
+
+    let inertias: Vec<f64> = ks
+        .iter()
+        .map(|&k| {
+            let mut rng = StdRng::seed_from_u64(ELBOW_SEED);
+            let run = lloyd(features, k, Distance::Euclidean, 100, &mut rng);
+            let inertia = inertia(features, &run.assignments, &run.centroids);
+            debug!("elbow sweep: k = {} has inertia {}", k, inertia);
+            on_k(k);
+            inertia
+        })
+        .collect();
+
+    // Scale both axes to [0, 1] so the distance doesn't depend on the units of inertia
+    let (first_k, last_k) = (ks[0] as f64, ks[ks.len() - 1] as f64);
+    let (first_inertia, last_inertia) = (inertias[0], inertias[inertias.len() - 1]);
+    let inertia_span = (first_inertia - last_inertia).abs().max(f64::EPSILON);
+    let points: Vec<(f64, f64)> = ks
+        .iter()
+        .zip(&inertias)
+        .map(|(&k, &inertia)| {
+            (
+                (k as f64 - first_k) / (last_k - first_k),
+                (inertia - last_inertia) / inertia_span,
+            )
+        })
+        .collect();
+
+    // The line runs from (0, y0) to (1, y1); measure perpendicular distance to it
+    let (x0, y0) = points[0];
+    let (x1, y1) = points[points.len() - 1];
+    let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+    let mut best = ks[0];
+    let mut best_distance = f64::NEG_INFINITY;
+    for (&k, &(x, y)) in ks.iter().zip(&points) {
+        let distance = ((y1 - y0) * x - (x1 - x0) * y + x1 * y0 - y1 * x0).abs() / length;
+        if distance > best_distance {
+            best = k;
+            best_distance = distance;
+        }
+    }
+
+    best
+}
+
+/// Pick `k` with the gap statistic (Tibshirani, Walther and Hastie): for every
+/// `k` in `k_range`, the gap is how far the log inertia of clustering
+/// `features` falls below its mean over `b_refs` reference datasets, each as
+/// many points drawn uniformly from the bounding box of `features`. Returns the
+/// smallest `k` whose gap is at least the next `k`'s gap minus that gap's
+/// standard error, or the largest `k` when none is. Values of `k` that are zero
+/// or exceed the number of feature vectors are skipped. The reference points
+/// and k-means seeding are drawn from RNGs seeded with `seed`, so equal inputs
+/// choose equal `k`.
+pub fn gap_statistic(
+    features: &[Vec<f64>],
+    k_range: RangeInclusive<usize>,
+    b_refs: usize,
+    seed: u64,
+) -> usize {
+    gap_statistic_with_progress(features, k_range, b_refs, seed, |_| {})
+}
+
+/// Like [`gap_statistic`], calling `on_k` with each `k` once its gap is known.
+pub fn gap_statistic_with_progress(
+    features: &[Vec<f64>],
+    k_range: RangeInclusive<usize>,
+    b_refs: usize,
+    seed: u64,
+    mut on_k: impl FnMut(usize),
+) -> usize {
+    let fallback = *k_range.start();
+    let ks: Vec<usize> = k_range.filter(|&k| k > 0 && k <= features.len()).collect();
+    if ks.len() < 2 {
+        return ks.first().copied().unwrap_or(fallback);
+    }
+
+    // Reference datasets, drawn once and reused for every k
+    let b_refs = b_refs.max(1);
+    let dims = features.iter().map(Vec::len).max().unwrap_or(0);
+    let bounds: Vec<(f64, f64)> = (0..dims)
+        .map(|d| {
+            let values = features.iter().filter_map(|point| point.get(d).copied());
+            values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), value| {
+                (lo.min(value), hi.max(value))
+            })
+        })
+        .collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let references: Vec<Vec<Vec<f64>>> = (0..b_refs)
+        .map(|_| {
+            (0..features.len())
+                .map(|_| {
+                    bounds
+                        .iter()
+                        .map(|&(lo, hi)| if lo < hi { rng.gen_range(lo..hi) } else { lo })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    // Gap and standard error of each k
+    let mut gaps = Vec::with_capacity(ks.len());
+    for &k in &ks {
+        let observed = log_dispersion(features, k, seed);
+        let reference: Vec<f64> = references
+            .iter()
+            .map(|points| log_dispersion(points, k, seed))
+            .collect();
+        let mean = reference.iter().sum::<f64>() / b_refs as f64;
+        let variance = reference.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / b_refs as f64;
+        let error = variance.sqrt() * (1.0 + 1.0 / b_refs as f64).sqrt();
+        debug!(
+            "gap statistic: k = {} has gap {} with standard error {}",
+            k,
+            mean - observed,
+            error
+        );
+        gaps.push((mean - observed, error));
+        on_k(k);
+    }
+
+    for (i, &k) in ks.iter().enumerate().take(ks.len() - 1) {
+        let (next_gap, next_error) = gaps[i + 1];
+        if gaps[i].0 >= next_gap - next_error {
+            return k;
+        }
+    }
+    ks[ks.len() - 1]
+}
+
+// Restarts of each k-means run of the gap statistic
+const GAP_RESTARTS: u64 = 5;
+
+// Log of the lowest inertia over a few seeded k-means runs; a perfect fit is
+// kept finite so the gap stays comparable
+fn log_dispersion(features: &[Vec<f64>], k: usize, seed: u64) -> f64 {
+    (0..GAP_RESTARTS)
+        .map(|restart| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(restart));
+            let run = lloyd(features, k, Distance::Euclidean, 100, &mut rng);
+            inertia(features, &run.assignments, &run.centroids)
+        })
+        .fold(f64::INFINITY, f64::min)
+        .max(f64::MIN_POSITIVE)
+        .ln()
+}
+
+/// One feature vector per record, with one dimension per entry of `categories`.
+/// Missing categories are filled with `0.0`.
+pub fn extract_features<T: GraphData>(data: &[T], categories: &[i32]) -> Vec<Vec<f64>> {
+    extract_features_with(data, categories, &[])
+}
+
+/// Like [`extract_features`], but a record missing `categories[i]` gets
+/// `fill[i]` in that dimension, e.g. from [`imputed_values`]. Categories past
+/// the end of `fill` are filled with `0.0`.
+pub fn extract_features_with<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+    fill: &[f64],
+) -> Vec<Vec<f64>> {
+    data.iter()
+        .map(|entry| {
+            categories
+                .iter()
+                .enumerate()
+                .map(|(i, &category)| {
+                    entry
+                        .get_weight(category)
+                        .unwrap_or_else(|| fill.get(i).copied().unwrap_or(0.0))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// How missing categories are filled in when building features. Zero reads
+/// "no data" as a value of zero, which pulls sparse municipalities together
+/// when the categories are rates; the others fill in a typical value instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Imputation {
+    /// Fill in `0.0`.
+    Zero,
+    /// Fill in the mean of the category over the records that have it.
+    Mean,
+    /// Fill in the median of the category over the records that have it.
+    Median,
+}
+
+impl FromStr for Imputation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "zero" => Ok(Imputation::Zero),
+            "mean" => Ok(Imputation::Mean),
+            "median" => Ok(Imputation::Median),
+            _ => Err(format!(
+                "unknown imputation '{}', expected zero, mean or median",
+                value
+            )),
+        }
+    }
+}
+
+/// The value `imputation` fills in for each entry of `categories`, computed
+/// over the records of `data` that have the category, for use with
+/// [`extract_features_with`]. A category no record has gets `0.0`.
+pub fn imputed_values<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+    imputation: Imputation,
+) -> Vec<f64> {
+    categories
+        .iter()
+        .map(|&category| {
+            let values: Vec<f64> = data
+                .iter()
+                .filter_map(|entry| entry.get_weight(category))
+                .collect();
+            if values.is_empty() {
+                return 0.0;
+            }
+            match imputation {
+                Imputation::Zero => 0.0,
+                Imputation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+                Imputation::Median => median(values),
+            }
+        })
+        .collect()
+}
+
+/// The entries of `categories` whose feature dimension, as built by
+/// [`extract_features`], holds the same value for every record, so it can't
+/// tell any municipalities apart. A category missing from every record counts,
+/// its dimension being all `0.0`. Empty `data` has no constant categories.
+pub fn constant_categories<T: GraphData>(data: &[T], categories: &[i32]) -> Vec<i32> {
+    let features = extract_features(data, categories);
+    let Some(first) = features.first() else {
+        return Vec::new();
+    };
+    categories
+        .iter()
+        .enumerate()
+        .filter(|&(d, _)| features.iter().all(|point| point[d] == first[d]))
+        .map(|(_, &category)| category)
+        .collect()
+}
+
+/// Feature vectors over both datasets for the municipalities present in both:
+/// the education weights over `categories` followed by the population growth
+/// weights over the same categories, with missing weights as `0.0`. Returns the
+/// municipality names alongside, in the same order. Municipalities in only one
+/// dataset are left out.
+pub fn joint_features(
+    edu: &[EducationData],
+    pop: &[PopGrowthData],
+    categories: &[i32],
+) -> (Vec<String>, Vec<Vec<f64>>) {
+    filter_common_municipalities(edu, pop)
+        .into_iter()
+        .map(|(municipality, edu_entry, pop_entry)| {
+            let weights = categories
+                .iter()
+                .map(|&category| edu_entry.get_weight(category).unwrap_or(0.0))
+                .chain(
+                    categories
+                        .iter()
+                        .map(|&category| pop_entry.get_weight(category).unwrap_or(0.0)),
+                )
+                .collect();
+            (municipality, weights)
+        })
+        .unzip()
+}
+
+/// Z-score every dimension in place: `(x - mean) / std`. Dimensions with zero
+/// standard deviation are only centered.
+pub fn standardize(features: &mut [Vec<f64>]) {
+    if features.is_empty() {
+        return;
+    }
+
+    for d in 0..features[0].len() {
+        let (mean, std) = mean_and_std(features, d);
+        for point in features.iter_mut() {
+            point[d] = z_score(point[d], mean, std);
+        }
+    }
+}
+
+/// Z-score `point` with the mean and standard deviation of each dimension over
+/// `reference`, so a new point lands where [`standardize`] would have put it had
+/// it been left out of `reference`'s statistics.
+pub fn standardize_point(reference: &[Vec<f64>], point: &mut [f64]) {
+    if reference.is_empty() {
+        return;
+    }
+    for (d, value) in point.iter_mut().enumerate() {
+        let (mean, std) = mean_and_std(reference, d);
+        *value = z_score(*value, mean, std);
+    }
+}
+
+// Population mean and standard deviation of dimension `d`; `features` is non-empty
+fn mean_and_std(features: &[Vec<f64>], d: usize) -> (f64, f64) {
+    let n = features.len() as f64;
+    let mean = features.iter().map(|point| point[d]).sum::<f64>() / n;
+    let variance = features
+        .iter()
+        .map(|point| (point[d] - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    (mean, variance.sqrt())
+}
+
+// Center `value`, and scale it too unless the dimension has no spread
+fn z_score(value: f64, mean: f64, std: f64) -> f64 {
+    if std > 0.0 {
+        (value - mean) / std
+    } else {
+        value - mean
+    }
+}
+
+/// How the distance between two clusters is measured in [`hierarchical_cluster`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    /// Distance between the closest pair of members.
+    Single,
+    /// Distance between the farthest pair of members.
+    Complete,
+    /// Mean distance over all pairs of members.
+    Average,
+}
+
+impl FromStr for Linkage {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "single" => Ok(Linkage::Single),
+            "complete" => Ok(Linkage::Complete),
+            "average" => Ok(Linkage::Average),
+            _ => Err(format!(
+                "unknown linkage '{}', expected single, complete or average",
+                value
+            )),
+        }
+    }
+}
+
+/// One step of agglomerative clustering. Clusters `0..n` are the input points,
+/// and merge `i` creates cluster `n + i`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Merge {
+    /// The smaller id of the two merged clusters.
+    pub left: usize,
+    /// The larger id of the two merged clusters.
+    pub right: usize,
+    /// The linkage distance between them.
+    pub distance: f64,
+    /// How many points the merged cluster holds.
+    pub size: usize,
+}
+
+/// The full merge history of agglomerative clustering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dendrogram {
+    /// How many points were clustered.
+    pub points: usize,
+    /// The merges, in the order they happened.
+    pub merges: Vec<Merge>,
+}
+
+impl Dendrogram {
+    /// Flat clusters from undoing every merge above `height`: the cluster index
+    /// of each point, numbered in order of each cluster's first point.
+    pub fn cut(&self, height: f64) -> Vec<usize> {
+        let mut union_find = UnionFind::new(self.points);
+        // A leaf standing in for every cluster id
+        let mut representative: Vec<usize> = (0..self.points).collect();
+        for merge in &self.merges {
+            let leaf = representative[merge.left];
+            if merge.distance <= height {
+                union_find.union(leaf, representative[merge.right]);
+            }
+            representative.push(leaf);
+        }
+
+        let mut labels = Vec::with_capacity(self.points);
+        let mut label_of_root: HashMap<usize, usize> = HashMap::new();
+        for point in 0..self.points {
+            let next = label_of_root.len();
+            labels.push(*label_of_root.entry(union_find.find(point)).or_insert(next));
+        }
+        labels
+    }
+}
+
+/// Agglomerative clustering: start with every point in its own cluster and
+/// repeatedly merge the two clusters closest under `linkage`, using Euclidean
+/// distances between points, until one cluster is left.
+pub fn hierarchical_cluster(features: &[Vec<f64>], linkage: Linkage) -> Dendrogram {
+    let n = features.len();
+    let mut distances: Vec<Vec<f64>> = features
+        .iter()
+        .map(|a| {
+            features
+                .iter()
+                .map(|b| squared_distance(a, b).sqrt())
+                .collect()
+        })
+        .collect();
+    // Rows of `distances` still in use, with the cluster id and size they stand for
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut ids: Vec<usize> = (0..n).collect();
+    let mut sizes = vec![1; n];
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+
+    while active.len() > 1 {
+        let mut closest = (active[0], active[1]);
+        for (i, &a) in active.iter().enumerate() {
+            for &b in &active[i + 1..] {
+                if distances[a][b] < distances[closest.0][closest.1] {
+                    closest = (a, b);
+                }
+            }
+        }
+        let (a, b) = closest;
+
+        // Row `a` becomes the merged cluster (Lance-Williams update)
+        for &c in &active {
+            if c == a || c == b {
+                continue;
+            }
+            let distance = match linkage {
+                Linkage::Single => distances[a][c].min(distances[b][c]),
+                Linkage::Complete => distances[a][c].max(distances[b][c]),
+                Linkage::Average => {
+                    (sizes[a] as f64 * distances[a][c] + sizes[b] as f64 * distances[b][c])
+                        / (sizes[a] + sizes[b]) as f64
+                }
+            };
+            distances[a][c] = distance;
+            distances[c][a] = distance;
+        }
+
+        merges.push(Merge {
+            left: ids[a].min(ids[b]),
+            right: ids[a].max(ids[b]),
+            distance: distances[a][b],
+            size: sizes[a] + sizes[b],
+        });
+        ids[a] = n + merges.len() - 1;
+        sizes[a] += sizes[b];
+        active.retain(|&c| c != b);
+    }
+
+    Dendrogram { points: n, merges }
+}
+
+/// Scale dimension `i` of every feature vector by `weights[i]`. Dimensions
+/// beyond the end of `weights` keep a weight of 1.
+pub fn weight_features(features: &mut [Vec<f64>], weights: &[f64]) {
+    for point in features.iter_mut() {
+        for (value, weight) in point.iter_mut().zip(weights) {
+            *value *= weight;
+        }
+    }
+}
+
+/// Project `features` onto their top `dims` principal components: center every
+/// dimension, take the eigenvectors of the covariance matrix with the largest
+/// eigenvalues, and return each point's coordinates along them. `dims` is capped
+/// at the number of feature dimensions.
+pub fn pca_project(features: &[Vec<f64>], dims: usize) -> Vec<Vec<f64>> {
+    if features.is_empty() {
+        return Vec::new();
+    }
+
+    let n = features.len();
+    let d = features[0].len();
+    let mut centered = DMatrix::from_fn(n, d, |i, j| features[i][j]);
+    for j in 0..d {
+        let mean = centered.column(j).mean();
+        centered.column_mut(j).add_scalar_mut(-mean);
+    }
+    let covariance = centered.transpose() * &centered / n as f64;
+
+    // Eigenvalues come unordered, so sort the components by explained variance
+    let eigen = covariance.symmetric_eigen();
+    let mut order: Vec<usize> = (0..d).collect();
+    order.sort_unstable_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+
+    let components = DMatrix::from_fn(d, dims.min(d), |i, j| eigen.eigenvectors[(i, order[j])]);
+    let projected = centered * components;
+    projected
+        .row_iter()
+        .map(|row| row.iter().copied().collect())
+        .collect()
+}
+
+// The outcome of one run of Lloyd's algorithm
+struct LloydRun {
+    assignments: Vec<usize>,
+    centroids: Vec<Vec<f64>>,
+    // Assignment passes made, counting the last one that changed nothing
+    iterations: usize,
+    converged: bool,
+    // Inertia once the centroids moved in each pass
+    inertia_history: Vec<f64>,
+}
+
+// Lloyd's algorithm, starting from `k` feature vectors picked by k-means++ seeding.
+// Stops once an assignment pass changes nothing, or after `max_iterations` passes.
+fn lloyd(
+    features: &[Vec<f64>],
+    k: usize,
+    metric: Distance,
+    max_iterations: usize,
+    rng: &mut StdRng,
+) -> LloydRun {
+    let k = k.min(features.len());
+    if k == 0 {
+        return LloydRun {
+            assignments: vec![0; features.len()],
+            centroids: Vec::new(),
+            iterations: 0,
+            converged: true,
+            inertia_history: Vec::new(),
+        };
+    }
+
+    let mut centroids = k_means_plus_plus(features, k, metric, rng);
+    let mut assignments = vec![0; features.len()];
+    let mut inertia_history = Vec::new();
+
+    // At least one pass, so every point gets assigned
+    let max_iterations = max_iterations.max(1);
+    for iteration in 1..=max_iterations {
+        // Points are matched in parallel; each match only reads the centroids, so
+        // the outcome doesn't depend on the thread count
+        let nearest: Vec<usize> = features
+            .par_iter()
+            .map(|point| nearest_centroid(&centroids, point, metric))
+            .collect();
+        let changed = nearest != assignments;
+        assignments = nearest;
+
+        // Move each centroid to the mean of its members, sequentially so the sums
+        // are always added up in the same order
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f64>> = features
+                .iter()
+                .zip(&assignments)
+                .filter(|&(_, &a)| a == cluster)
+                .map(|(point, _)| point)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            for (d, value) in centroid.iter_mut().enumerate() {
+                *value = match metric {
+                    Distance::Manhattan => median(members.iter().map(|point| point[d]).collect()),
+                    _ => members.iter().map(|point| point[d]).sum::<f64>() / members.len() as f64,
+                };
+            }
+        }
+        inertia_history.push(metric_inertia(features, &assignments, &centroids, metric));
+
+        if !changed {
+            debug!(
+                "k-means with k = {} converged after {} iterations",
+                k, iteration
+            );
+            return LloydRun {
+                assignments,
+                centroids,
+                iterations: iteration,
+                converged: true,
+                inertia_history,
+            };
+        }
+    }
+
+    debug!(
+        "k-means with k = {} stopped after {} iterations without converging",
+        k, max_iterations
+    );
+    LloydRun {
+        assignments,
+        centroids,
+        iterations: max_iterations,
+        converged: false,
+        inertia_history,
+    }
+}
+
+// k-means++ seeding: the first centroid is a uniformly random feature vector, and
+// each next one is drawn with probability proportional to its squared distance
+// from the nearest centroid picked so far
+fn k_means_plus_plus(
+    features: &[Vec<f64>],
+    k: usize,
+    metric: Distance,
+    rng: &mut StdRng,
+) -> Vec<Vec<f64>> {
+    let mut centroids = vec![features[rng.gen_range(0..features.len())].clone()];
+    let mut distances: Vec<f64> = features
+        .iter()
+        .map(|point| squared_dist(point, &centroids[0], metric))
+        .collect();
+
+    while centroids.len() < k {
+        // Only duplicates of the centroids are left, so any of them will do
+        let next = match WeightedIndex::new(&distances) {
+            Ok(weighted) => weighted.sample(rng),
+            Err(_) => rng.gen_range(0..features.len()),
+        };
+        centroids.push(features[next].clone());
+        for (distance, point) in distances.iter_mut().zip(features) {
+            *distance = distance.min(squared_dist(point, &features[next], metric));
+        }
+    }
+
+    centroids
+}
+
+fn nearest_centroid(centroids: &[Vec<f64>], point: &[f64], metric: Distance) -> usize {
+    let mut best = 0;
+    let mut best_distance = f64::INFINITY;
+    for (i, centroid) in centroids.iter().enumerate() {
+        let distance = squared_dist(centroid, point, metric);
+        if distance < best_distance {
+            best = i;
+            best_distance = distance;
+        }
+    }
+    best
+}
+
+// The middle value, or the mean of the two middle values; `values` is non-empty
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_unstable_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+// `dist` squared, without the round trip through a square root for Euclidean
+fn squared_dist(a: &[f64], b: &[f64], metric: Distance) -> f64 {
+    match metric {
+        Distance::Euclidean => squared_distance(a, b),
+        _ => dist(a, b, metric).powi(2),
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::records;
+
+    // Twenty points on a small grid around each of three distant centers
+    fn three_blobs() -> Vec<Vec<f64>> {
+        [(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)]
+            .iter()
+            .flat_map(|&(x, y)| {
+                (0..20).map(move |i| vec![x + (i % 5) as f64 * 0.2, y + (i / 5) as f64 * 0.2])
+            })
+            .collect()
+    }
+
+    // The members of `cluster`, sorted
+    fn members(result: &ClusteringResult, cluster: usize) -> Vec<&str> {
+        let mut members: Vec<&str> = result.assignments[&cluster]
+            .iter()
+            .map(String::as_str)
+            .collect();
+        members.sort_unstable();
+        members
+    }
+
+    #[test]
+    fn a_point_on_a_centroid_joins_its_cluster() {
+        let centroids = vec![vec![0.0, 0.0], vec![10.0, 0.0], vec![3.0, 4.0]];
+        for metric in [
+            Distance::Euclidean,
+            Distance::Manhattan,
+            Distance::Chebyshev,
+        ] {
+            for (cluster, centroid) in centroids.iter().enumerate() {
+                assert_eq!(
+                    assign_to_nearest_centroid(&centroids, centroid, metric),
+                    cluster
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn new_points_are_assigned_under_the_given_metric() {
+        // [4, 0] is nearer [2.9, 3.2] in Euclidean and Chebyshev distance, but
+        // nearer the origin in Manhattan distance
+        let centroids = vec![vec![0.0, 0.0], vec![2.9, 3.2]];
+        let point = [4.0, 0.0];
+        assert_eq!(
+            assign_to_nearest_centroid(&centroids, &point, Distance::Euclidean),
+            1
+        );
+        assert_eq!(
+            assign_to_nearest_centroid(&centroids, &point, Distance::Manhattan),
+            0
+        );
+        assert_eq!(
+            assign_to_nearest_centroid(&centroids, &point, Distance::Chebyshev),
+            1
+        );
+    }
+
+    #[test]
+    fn gap_statistic_finds_three_blobs() {
+        assert_eq!(gap_statistic(&three_blobs(), 1..=6, 10, 7), 3);
+    }
+
+    #[test]
+    fn constant_categories_are_detected() {
+        let mut data = Vec::new();
+        for (i, name) in ["Alpha_North", "Alpha_South", "Beta_North"]
+            .iter()
+            .enumerate()
+        {
+            let mut entry = EducationData::new(*name);
+            entry.set(1, 2020, i as f64);
+            entry.set(2, 2020, 42.0);
+            if i > 0 {
+                entry.set(3, 2020, 0.0);
+            }
+            data.push(entry);
+        }
+        // Category 3 is missing from Alpha_North, which reads as 0.0 like the others
+        assert_eq!(constant_categories(&data, &[1, 2, 3]), [2, 3]);
+        assert_eq!(constant_categories(&data, &[1]), Vec::<i32>::new());
+
+        data[2].set(3, 2020, 1.0);
+        assert_eq!(constant_categories(&data, &[1, 2, 3]), [2]);
+    }
+
+    #[test]
+    fn fuzzy_memberships_sum_to_one_and_favor_nearby_clusters() {
+        // Two tight groups of repeated points and one point halfway between them
+        let mut features = vec![vec![0.0, 0.0]; 5];
+        features.extend(vec![vec![10.0, 10.0]; 5]);
+        features.push(vec![5.0, 5.0]);
+        let memberships = fuzzy_cmeans(&features, 2, 2.0, 3);
+
+        assert_eq!(memberships.len(), features.len());
+        for row in &memberships {
+            assert_eq!(row.len(), 2);
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        // The groups sit on their centroids, and are in different clusters
+        let (a, b) = (&memberships[0], &memberships[5]);
+        assert!(a.iter().any(|&p| p > 0.999));
+        assert!(b.iter().any(|&p| p > 0.999));
+        assert!((a[0] - b[0]).abs() > 0.99);
+        assert!((memberships[10][0] - 0.5).abs() < 0.01);
+
+        let names: Vec<String> = (0..features.len()).map(|i| format!("m{:02}", i)).collect();
+        let ranked = most_ambiguous(&names, &memberships, 2);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].0, "m10");
+        assert!((ranked[0].1 - 2.0f64.ln()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mean_imputation_fills_in_the_average_of_present_values() {
+        let mut data = Vec::new();
+        for (name, literacy) in [
+            ("A", Some(90.0)),
+            ("B", Some(60.0)),
+            ("C", Some(51.0)),
+            ("D", None),
+        ] {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, 1.0);
+            if let Some(literacy) = literacy {
+                entry.set(2, 2020, literacy);
+            }
+            data.push(entry);
+        }
+
+        let mean = imputed_values(&data, &[1, 2, 3], Imputation::Mean);
+        assert_eq!(mean, [1.0, 67.0, 0.0]);
+        let features = extract_features_with(&data, &[1, 2], &mean);
+        assert_eq!(features[3], [1.0, 67.0]);
+        assert_eq!(features[0], [1.0, 90.0]);
+
+        assert_eq!(imputed_values(&data, &[2], Imputation::Median), [60.0]);
+        assert_eq!(imputed_values(&data, &[2], Imputation::Zero), [0.0]);
+        assert_eq!("median".parse::<Imputation>(), Ok(Imputation::Median));
+    }
+
+    #[test]
+    fn inertia_history_has_one_falling_entry_per_iteration() {
+        // Scattered points, which take k-means several iterations to settle
+        let features: Vec<Vec<f64>> = (0..60)
+            .map(|i| vec![(i * 37 % 101) as f64 / 10.0, (i * 53 % 97) as f64 / 10.0])
+            .collect();
+        let municipalities: Vec<String> = (0..60).map(|i| format!("M{}", i)).collect();
+        let config = KMeansConfig {
+            n_init: 1,
+            ..KMeansConfig::new(4)
+        };
+        let result = k_means_features(&municipalities, &features, &config).unwrap();
+
+        let history = &result.inertia_history;
+        assert!(result.iterations > 1);
+        assert_eq!(history.len(), result.iterations);
+        assert!(history.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!((history[history.len() - 1] - result.inertia).abs() < 1e-9);
+    }
+
+    #[test]
+    fn features_cover_the_given_categories_with_zero_for_missing() {
+        // The two municipalities share no category
+        let data = records(&[("A", &[(1, 4.0), (2, 6.0)]), ("B", &[(11, 3.0), (12, 5.0)])]);
+
+        let features = extract_features(&data, &[1, 2, 11, 12]);
+        assert_eq!(features, [[4.0, 6.0, 0.0, 0.0], [0.0, 0.0, 3.0, 5.0]]);
+        // Categories outside the old 1..=10 range count like any other
+        assert_eq!(extract_features(&data, &[12]), [[0.0], [5.0]]);
+
+        let result =
+            k_means_clustering(&data, &[1, 2, 11, 12], &[], false, &KMeansConfig::new(2)).unwrap();
+        assert_ne!(result.labels[0], result.labels[1]);
+    }
+
+    #[test]
+    fn standardizing_keeps_a_large_scale_category_from_dominating() {
+        // Category 1 is spread evenly over 0..=1000, category 2 splits the
+        // municipalities into two groups on a 0..=1 scale
+        let data = records(&[
+            ("A", &[(1, 0.0), (2, 0.0)]),
+            ("B", &[(1, 200.0), (2, 1.0)]),
+            ("C", &[(1, 400.0), (2, 0.0)]),
+            ("D", &[(1, 600.0), (2, 1.0)]),
+            ("E", &[(1, 800.0), (2, 0.0)]),
+            ("F", &[(1, 1000.0), (2, 1.0)]),
+        ]);
+        let config = KMeansConfig::new(2);
+
+        // Raw, A and E are 800 apart on category 1 and end up apart
+        let raw = k_means_clustering(&data, &[1, 2], &[], false, &config).unwrap();
+        assert_ne!(raw.labels[0], raw.labels[4]);
+        assert_eq!(members(&raw, raw.labels[0]), ["A", "B", "C"]);
+
+        // Standardized, both categories count alike and category 2 decides
+        let standardized = k_means_clustering(&data, &[1, 2], &[], true, &config).unwrap();
+        assert_eq!(standardized.labels[0], standardized.labels[4]);
+        assert_eq!(
+            members(&standardized, standardized.labels[0]),
+            ["A", "C", "E"]
+        );
+    }
+
+    #[test]
+    fn equal_seeds_give_equal_clusters() {
+        let data: Vec<EducationData> = (0..30)
+            .map(|i| {
+                let mut entry = EducationData::new(format!("M{:02}", i));
+                entry.set(1, 2020, (i * 37 % 101) as f64);
+                entry.set(2, 2020, (i * 53 % 97) as f64);
+                entry
+            })
+            .collect();
+        let config = KMeansConfig {
+            seed: 42,
+            n_init: 1,
+            ..KMeansConfig::new(3)
+        };
+
+        let first = k_means_clustering(&data, &[1, 2], &[], true, &config).unwrap();
+        let second = k_means_clustering(&data, &[1, 2], &[], true, &config).unwrap();
+        assert_eq!(first.assignments, second.assignments);
+        assert_eq!(first.labels, second.labels);
+        assert_eq!(first.centroids, second.centroids);
+        assert_eq!(first.inertia.to_bits(), second.inertia.to_bits());
+    }
+
+    #[test]
+    fn inertia_does_not_grow_with_k() {
+        let features: Vec<Vec<f64>> = (0..40)
+            .map(|i| vec![(i * 37 % 101) as f64, (i * 53 % 97) as f64])
+            .collect();
+        let municipalities: Vec<String> = (0..40).map(|i| format!("M{}", i)).collect();
+
+        let inertias: Vec<f64> = (2..=4)
+            .map(|k| {
+                let result =
+                    k_means_features(&municipalities, &features, &KMeansConfig::new(k)).unwrap();
+                assert_eq!(result.centroids.len(), k);
+                assert!(
+                    (result.inertia - inertia(&features, &result.labels, &result.centroids)).abs()
+                        < 1e-9
+                );
+                result.inertia
+            })
+            .collect();
+        assert!(
+            inertias.windows(2).all(|pair| pair[1] <= pair[0]),
+            "{:?}",
+            inertias
+        );
+    }
+
+    #[test]
+    fn elbow_finds_three_blobs() {
+        assert_eq!(best_k(&three_blobs(), 1..=8), 3);
+    }
+
+    #[test]
+    fn separated_blobs_have_a_high_silhouette() {
+        let features: Vec<Vec<f64>> = (0..20)
+            .map(|i| {
+                let offset = if i < 10 { 0.0 } else { 50.0 };
+                vec![offset + (i % 5) as f64 * 0.5, (i % 3) as f64 * 0.5]
+            })
+            .collect();
+        let labels: Vec<usize> = (0..20).map(|i| usize::from(i >= 10)).collect();
+        assert!(silhouette_score(&features, &labels) > 0.7);
+
+        // Clusters that each take half of both blobs score far worse
+        let mixed: Vec<usize> = (0..20).map(|i| i % 2).collect();
+        assert!(silhouette_score(&features, &mixed) < 0.1);
+        assert_eq!(silhouette_score(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn pca_recovers_a_plane_embedded_in_four_dimensions() {
+        // Orthonormal directions spanning the plane, plus an offset
+        let u = [0.5, 0.5, 0.5, 0.5];
+        let v = [0.5, -0.5, 0.5, -0.5];
+        let features: Vec<Vec<f64>> = (0..25)
+            .map(|i| {
+                let (a, b) = ((i % 5) as f64 * 3.0, (i / 5) as f64 - 2.0);
+                (0..4).map(|d| 10.0 + a * u[d] + b * v[d]).collect()
+            })
+            .collect();
+        let variance = |points: &[Vec<f64>]| -> f64 {
+            let n = points.len() as f64;
+            (0..points[0].len())
+                .map(|d| {
+                    let mean = points.iter().map(|p| p[d]).sum::<f64>() / n;
+                    points.iter().map(|p| (p[d] - mean).powi(2)).sum::<f64>() / n
+                })
+                .sum()
+        };
+
+        let projected = pca_project(&features, 2);
+        assert_eq!(projected.len(), 25);
+        assert!(projected.iter().all(|point| point.len() == 2));
+        assert!((variance(&projected) - variance(&features)).abs() < 1e-9);
+        // The first component runs along the wider direction
+        let first: Vec<Vec<f64>> = projected.iter().map(|point| vec![point[0]]).collect();
+        assert!((variance(&first) - 18.0).abs() < 1e-9);
+        // A third component finds nothing left
+        let third: Vec<Vec<f64>> = pca_project(&features, 3)
+            .iter()
+            .map(|point| vec![point[2]])
+            .collect();
+        assert!(variance(&third) < 1e-9);
+    }
+
+    #[test]
+    fn dbscan_labels_an_isolated_point_noise() {
+        let mut features: Vec<Vec<f64>> = (0..5).map(|i| vec![i as f64 * 0.1, 0.0]).collect();
+        features.extend((0..5).map(|i| vec![20.0 + i as f64 * 0.1, 20.0]));
+        features.push(vec![10.0, 10.0]);
+
+        let labels = dbscan(&features, 0.5, 3);
+        assert!(labels[..5].iter().all(|&label| label == Some(0)));
+        assert!(labels[5..10].iter().all(|&label| label == Some(1)));
+        assert_eq!(labels[10], None);
+    }
+
+    #[test]
+    fn doubling_a_weight_regroups_a_rectangle_along_that_dimension() {
+        // The corners of a rectangle wider than it is tall
+        let data = records(&[
+            ("A", &[(1, 0.0), (2, 0.0)]),
+            ("B", &[(1, 4.0), (2, 0.0)]),
+            ("C", &[(1, 0.0), (2, 3.0)]),
+            ("D", &[(1, 4.0), (2, 3.0)]),
+        ]);
+        let config = KMeansConfig::new(2);
+
+        let unweighted = k_means_clustering(&data, &[1, 2], &[], false, &config).unwrap();
+        assert_eq!(unweighted.labels[0], unweighted.labels[2]);
+        assert_ne!(unweighted.labels[0], unweighted.labels[1]);
+
+        // Doubled, the height outweighs the width
+        let weighted = k_means_clustering(&data, &[1, 2], &[1.0, 2.0], false, &config).unwrap();
+        assert_eq!(weighted.labels[0], weighted.labels[1]);
+        assert_ne!(weighted.labels[0], weighted.labels[2]);
+    }
+
+    #[test]
+    fn the_first_merge_joins_the_two_closest_points() {
+        let features = vec![vec![0.0], vec![10.0], vec![10.5], vec![4.0]];
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average] {
+            let dendrogram = hierarchical_cluster(&features, linkage);
+            assert_eq!(dendrogram.merges.len(), 3);
+            let first = &dendrogram.merges[0];
+            assert_eq!((first.left, first.right), (1, 2));
+            assert_eq!(first.distance, 0.5);
+            assert_eq!(first.size, 2);
+        }
+    }
+
+    #[test]
+    fn a_far_point_is_the_only_outlier_of_its_cluster() {
+        // Nine points on top of each other and one far away
+        let mut features = vec![vec![0.0]; 9];
+        features.push(vec![100.0]);
+        let municipalities: Vec<String> = (0..10).map(|i| format!("M{}", i)).collect();
+        let result = k_means_features(&municipalities, &features, &KMeansConfig::new(1)).unwrap();
+
+        // The far point lies three standard deviations above the mean distance
+        assert_eq!(cluster_outliers(&result, &features, 2.0), vec!["M9"]);
+        assert!(cluster_outliers(&result, &features, 3.5).is_empty());
+    }
+
+    #[test]
+    fn joint_features_cover_both_datasets_for_common_municipalities() {
+        let edu = records(&[("Alpha", &[(1, 10.0), (2, 20.0)]), ("Beta", &[(1, 30.0)])]);
+        let mut alpha = PopGrowthData::new("Alpha");
+        alpha.set(2, 2020, -1.5);
+        let mut gamma = PopGrowthData::new("Gamma");
+        gamma.set(1, 2020, 4.0);
+
+        // Beta has no growth and Gamma no education, so only Alpha is left
+        let (names, features) = joint_features(&edu, &[alpha, gamma], &[1, 2]);
+        assert_eq!(names, vec!["Alpha"]);
+        assert_eq!(features, vec![vec![10.0, 20.0, 0.0, -1.5]]);
+    }
+
+    #[test]
+    fn one_k_means_plus_plus_restart_recovers_three_blobs() {
+        let features = three_blobs();
+        let municipalities: Vec<String> = (0..features.len()).map(|i| format!("M{}", i)).collect();
+        for seed in 0..10 {
+            let config = KMeansConfig {
+                seed,
+                n_init: 1,
+                ..KMeansConfig::new(3)
+            };
+            let result = k_means_features(&municipalities, &features, &config).unwrap();
+            for blob in result.labels.chunks(20) {
+                assert!(blob.iter().all(|&label| label == blob[0]), "seed {}", seed);
+            }
+            assert_eq!(result.assignments.len(), 3, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn more_restarts_never_raise_the_inertia() {
+        // Points without an obvious clustering, so restarts disagree
+        let features: Vec<Vec<f64>> = (0..40)
+            .map(|i| vec![((i * 37) % 17) as f64, ((i * 53) % 23) as f64])
+            .collect();
+        let municipalities: Vec<String> = (0..features.len()).map(|i| format!("M{}", i)).collect();
+
+        let mut previous = f64::INFINITY;
+        for n_init in 1..=8 {
+            let config = KMeansConfig {
+                n_init,
+                ..KMeansConfig::new(4)
+            };
+            let inertia = k_means_features(&municipalities, &features, &config)
+                .unwrap()
+                .inertia;
+            assert!(inertia <= previous, "n_init {}", n_init);
+            previous = inertia;
+        }
+    }
+
+    #[test]
+    fn an_empty_dataset_is_an_error_rather_than_a_panic() {
+        let empty: Vec<EducationData> = Vec::new();
+        let result = k_means_clustering(&empty, &[1], &[], true, &KMeansConfig::new(3));
+        assert!(matches!(
+            result,
+            Err(AppError::ClusterCount { k: 3, records: 0 })
+        ));
+
+        // So is asking for more clusters than there are records
+        let data = records(&[("Alpha", &[(1, 1.0)]), ("Beta", &[(1, 2.0)])]);
+        let result = k_means_clustering(&data, &[1], &[], true, &KMeansConfig::new(3));
+        assert!(matches!(
+            result,
+            Err(AppError::ClusterCount { k: 3, records: 2 })
+        ));
+    }
+
+    #[test]
+    fn distances_from_the_origin_to_three_four() {
+        let (origin, point) = ([0.0, 0.0], [3.0, 4.0]);
+        assert_eq!(dist(&origin, &point, Distance::Manhattan), 7.0);
+        assert_eq!(dist(&origin, &point, Distance::Euclidean), 5.0);
+        assert_eq!(dist(&origin, &point, Distance::Chebyshev), 4.0);
+    }
+
+    #[test]
+    fn separated_blobs_converge_long_before_the_iteration_cap() {
+        let features = three_blobs();
+        let municipalities: Vec<String> = (0..features.len()).map(|i| format!("M{}", i)).collect();
+        let result = k_means_features(&municipalities, &features, &KMeansConfig::new(3)).unwrap();
+
+        assert!(result.converged);
+        assert!(result.iterations >= 1 && result.iterations <= 10);
+        assert_eq!(result.inertia_history.len(), result.iterations);
+
+        // Cut off after one iteration, a run stops before converging
+        let config = KMeansConfig {
+            max_iterations: 1,
+            n_init: 1,
+            ..KMeansConfig::new(3)
+        };
+        let capped = k_means_features(&municipalities, &features, &config).unwrap();
+        assert_eq!(capped.iterations, 1);
+        assert!(!capped.converged);
+    }
+
+    #[test]
+    fn medoids_are_input_points() {
+        let features = vec![
+            vec![0.0, 0.0],
+            vec![1.0, 0.5],
+            vec![0.5, 1.0],
+            vec![10.0, 10.0],
+            vec![11.0, 10.5],
+            vec![40.0, 40.0],
+        ];
+        let municipalities: Vec<String> = (0..features.len()).map(|i| format!("M{}", i)).collect();
+        let result = k_medoids(&municipalities, &features, 2, 0).unwrap();
+
+        assert_eq!(result.medoids.len(), 2);
+        for (cluster, (centroid, &medoid)) in
+            result.centroids.iter().zip(&result.medoids).enumerate()
+        {
+            assert!(medoid < features.len());
+            assert_eq!(centroid, &features[medoid]);
+            // Each medoid belongs to the cluster it centers
+            assert_eq!(result.labels[medoid], cluster);
+        }
+    }
+
+    #[test]
+    fn cluster_numbers_survive_shuffling_the_input() {
+        let features = three_blobs();
+        let municipalities: Vec<String> =
+            (0..features.len()).map(|i| format!("M{:02}", i)).collect();
+        let result = k_means_features(&municipalities, &features, &KMeansConfig::new(3)).unwrap();
+
+        let (reversed_names, reversed_features): (Vec<String>, Vec<Vec<f64>>) = municipalities
+            .iter()
+            .cloned()
+            .zip(features.iter().cloned())
+            .rev()
+            .unzip();
+        let reversed =
+            k_means_features(&reversed_names, &reversed_features, &KMeansConfig::new(3)).unwrap();
+        assert_eq!(
+            cluster_by_name(&result.assignments),
+            cluster_by_name(&reversed.assignments)
+        );
+        // Cluster 0 holds the alphabetically first municipality
+        assert_eq!(result.labels[0], 0);
+
+        // Bare assignments under swapped labels are renumbered the same way
+        let swapped = HashMap::from([
+            (0, vec!["Gamma".to_string()]),
+            (1, vec!["Beta".to_string(), "Alpha".to_string()]),
+        ]);
+        let canonical = canonicalize_assignments(swapped);
+        assert_eq!(canonical[&0], ["Beta", "Alpha"]);
+        assert_eq!(canonical[&1], ["Gamma"]);
+    }
+
+    #[test]
+    fn haversine_matches_known_city_distances() {
+        let paris = (48.8566, 2.3522);
+        let london = (51.5074, -0.1278);
+        let new_york = (40.7128, -74.0060);
+        let los_angeles = (34.0522, -118.2437);
+
+        for (a, b, reference_km) in [(paris, london, 343.5), (new_york, los_angeles, 3936.0)] {
+            let km = haversine(a, b);
+            assert!((km - reference_km).abs() / reference_km < 0.01, "{} km", km);
+            assert_eq!(haversine(b, a), km);
+        }
+        assert_eq!(haversine(paris, paris), 0.0);
+    }
+}
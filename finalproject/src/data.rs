@@ -0,0 +1,1407 @@
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::AppError;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EducationData {
+    municipality: String,
+    data: HashMap<i32, (i32, f64)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PopGrowthData {
+    municipality: String,
+    data: HashMap<i32, (i32, f64)>,
+}
+
+impl EducationData {
+    /// A record for `municipality` without any categories yet.
+    pub fn new(municipality: impl Into<String>) -> Self {
+        EducationData {
+            municipality: municipality.into(),
+            data: HashMap::new(),
+        }
+    }
+
+    /// Set `category` to `value`, measured in `year`, replacing any earlier value.
+    pub fn set(&mut self, category: i32, year: i32, value: f64) {
+        self.data.insert(category, (year, value));
+    }
+}
+
+impl PopGrowthData {
+    /// A record for `municipality` without any categories yet.
+    pub fn new(municipality: impl Into<String>) -> Self {
+        PopGrowthData {
+            municipality: municipality.into(),
+            data: HashMap::new(),
+        }
+    }
+
+    /// Set `category` to `value`, measured in `year`, replacing any earlier value.
+    pub fn set(&mut self, category: i32, year: i32, value: f64) {
+        self.data.insert(category, (year, value));
+    }
+}
+
+pub trait GraphData {
+    fn municipality(&self) -> &str;
+    fn get_weight(&self, category: i32) -> Option<f64>;
+    fn get_year(&self, category: i32) -> Option<i32>;
+    fn categories(&self) -> Vec<i32>;
+}
+
+/// Construction from the raw parts of a record, used by the row-based readers.
+pub trait FromParts {
+    fn from_parts(municipality: String, data: HashMap<i32, (i32, f64)>) -> Self;
+}
+
+impl FromParts for EducationData {
+    fn from_parts(municipality: String, data: HashMap<i32, (i32, f64)>) -> Self {
+        EducationData { municipality, data }
+    }
+}
+
+impl FromParts for PopGrowthData {
+    fn from_parts(municipality: String, data: HashMap<i32, (i32, f64)>) -> Self {
+        PopGrowthData { municipality, data }
+    }
+}
+
+impl GraphData for EducationData {
+    fn municipality(&self) -> &str {
+        &self.municipality
+    }
+
+    fn get_weight(&self, category: i32) -> Option<f64> {
+        self.data.get(&category).map(|&(_, weight)| weight)
+    }
+
+    fn get_year(&self, category: i32) -> Option<i32> {
+        self.data.get(&category).map(|&(year, _)| year)
+    }
+
+    fn categories(&self) -> Vec<i32> {
+        let mut categories: Vec<i32> = self.data.keys().copied().collect();
+        categories.sort_unstable();
+        categories
+    }
+}
+
+impl GraphData for PopGrowthData {
+    fn municipality(&self) -> &str {
+        &self.municipality
+    }
+
+    fn get_weight(&self, category: i32) -> Option<f64> {
+        self.data.get(&category).map(|&(_, weight)| weight)
+    }
+
+    fn get_year(&self, category: i32) -> Option<i32> {
+        self.data.get(&category).map(|&(year, _)| year)
+    }
+
+    fn categories(&self) -> Vec<i32> {
+        let mut categories: Vec<i32> = self.data.keys().copied().collect();
+        categories.sort_unstable();
+        categories
+    }
+}
+
+pub fn read_data<T: for<'de> Deserialize<'de>>(file_path: &str) -> Result<Vec<T>, AppError> {
+    let file = open_file(file_path)?;
+    parse_json(io::BufReader::new(file)).map_err(|err| err.in_file(file_path))
+}
+
+/// Parse a JSON array of records from any reader, such as stdin or an in-memory buffer.
+pub fn parse_json<T: for<'de> Deserialize<'de>, R: io::Read>(
+    reader: R,
+) -> Result<Vec<T>, AppError> {
+    let records: Vec<T> = serde_json::from_reader(reader)?;
+    Ok(records)
+}
+
+/// Read a data file, choosing the parser from its extension: `.csv` files are read
+/// with `read_data_csv`, `.tsv` files likewise but split on tabs, `.ndjson` and
+/// `.jsonl` files with `read_data_ndjson`, and everything else is treated as a
+/// JSON array. A trailing `.gz` marks a gzip-compressed file and is ignored when
+/// choosing the parser. The path `-` reads a JSON array from stdin. A file
+/// without any records is an error.
+pub fn read_input<T>(file_path: &str) -> Result<Vec<T>, AppError>
+where
+    T: for<'de> Deserialize<'de> + FromParts,
+{
+    read_input_with(file_path, None)
+}
+
+/// Like [`read_input`], but with `Some(delimiter)` the file is read as delimited
+/// text split on `delimiter` whatever its extension.
+pub fn read_input_with<T>(file_path: &str, delimiter: Option<Delimiter>) -> Result<Vec<T>, AppError>
+where
+    T: for<'de> Deserialize<'de> + FromParts,
+{
+    let format_path = file_path.strip_suffix(".gz").unwrap_or(file_path);
+    let delimiter = delimiter.or_else(|| Delimiter::from_extension(format_path));
+    let records = if let Some(delimiter) = delimiter {
+        read_data_csv_with(file_path, delimiter)?
+    } else if format_path.ends_with(".ndjson") || format_path.ends_with(".jsonl") {
+        read_data_ndjson(file_path)?
+    } else {
+        read_data(file_path)?
+    };
+
+    if records.is_empty() {
+        return Err(AppError::EmptyDataset(file_path.to_string()));
+    }
+    info!("read {} records from '{}'", records.len(), file_path);
+    Ok(records)
+}
+
+/// Read several data files into one dataset, each with [`read_input_with`], in
+/// order. Municipalities found in more than one file, or repeated within one,
+/// are collapsed according to `policy` (see [`deduplicate`]), so files split by
+/// year or region can be combined as if they were one. Fails if any file fails
+/// to read.
+pub fn read_inputs<T>(
+    file_paths: &[String],
+    delimiter: Option<Delimiter>,
+    policy: DuplicatePolicy,
+) -> Result<Vec<T>, AppError>
+where
+    T: for<'de> Deserialize<'de> + FromParts + GraphData,
+{
+    let mut records = Vec::new();
+    for file_path in file_paths {
+        records.extend(read_input_with::<T>(file_path, delimiter)?);
+    }
+    deduplicate(records, policy)
+}
+
+/// Read a file holding one JSON record per line, through [`read_data_iter`].
+pub fn read_data_ndjson<T: for<'de> Deserialize<'de>>(file_path: &str) -> Result<Vec<T>, AppError> {
+    read_data_iter(file_path).collect()
+}
+
+/// Lazily read a file holding one JSON record per line, so records can be
+/// processed without holding the whole file in memory. Failing to open the
+/// file is reported as the only item.
+pub fn read_data_iter<T: for<'de> Deserialize<'de>>(
+    file_path: &str,
+) -> impl Iterator<Item = Result<T, AppError>> {
+    let (open_error, records) = match open_file(file_path) {
+        Ok(file) => (None, Some(parse_ndjson_iter(io::BufReader::new(file)))),
+        Err(err) => (Some(Err(err)), None),
+    };
+    let file_path = file_path.to_string();
+    open_error
+        .into_iter()
+        .chain(records.into_iter().flatten())
+        .map(move |record| record.map_err(|err| err.in_file(&file_path)))
+}
+
+/// Parse one JSON record per line, skipping blank lines. Parse failures name
+/// the (1-based) line they occurred on.
+pub fn parse_ndjson<T: for<'de> Deserialize<'de>, R: BufRead>(
+    reader: R,
+) -> Result<Vec<T>, AppError> {
+    parse_ndjson_iter(reader).collect()
+}
+
+/// Like `parse_ndjson`, but yielding the records one at a time as they are read.
+pub fn parse_ndjson_iter<T: for<'de> Deserialize<'de>, R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<T, AppError>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(serde_json::from_str(&line).map_err(|err| AppError::Parse {
+                line: i + 1,
+                message: err.to_string(),
+            })),
+            Err(err) => Some(Err(err.into())),
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    municipality: String,
+    year: i32,
+    category: i32,
+    value: f64,
+}
+
+/// The field separator of a delimited data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    /// The separator implied by a `.csv` or `.tsv` extension, if any.
+    pub fn from_extension(file_path: &str) -> Option<Self> {
+        if file_path.ends_with(".csv") {
+            Some(Delimiter::Comma)
+        } else if file_path.ends_with(".tsv") {
+            Some(Delimiter::Tab)
+        } else {
+            None
+        }
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            Delimiter::Comma => b',',
+            Delimiter::Tab => b'\t',
+        }
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "comma" => Ok(Delimiter::Comma),
+            "tab" => Ok(Delimiter::Tab),
+            _ => Err(format!(
+                "unknown delimiter '{}', expected comma or tab",
+                value
+            )),
+        }
+    }
+}
+
+/// Read a CSV file with a `municipality,year,category,value` header.
+pub fn read_data_csv<T: FromParts>(file_path: &str) -> Result<Vec<T>, AppError> {
+    read_data_csv_with(file_path, Delimiter::Comma)
+}
+
+/// Like [`read_data_csv`], with fields separated by `delimiter`.
+pub fn read_data_csv_with<T: FromParts>(
+    file_path: &str,
+    delimiter: Delimiter,
+) -> Result<Vec<T>, AppError> {
+    let file = open_file(file_path)?;
+    parse_csv_with(file, delimiter).map_err(|err| err.in_file(file_path))
+}
+
+// Open an input file, naming it in the error so the user knows which one is missing.
+// Files ending in `.gz` are decompressed as they are read, and `-` is stdin.
+fn open_file(file_path: &str) -> Result<Box<dyn io::Read>, AppError> {
+    if file_path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(file_path).map_err(|source| AppError::Open {
+        path: file_path.to_string(),
+        source,
+    })?;
+    if file_path.ends_with(".gz") {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Group CSV rows by municipality, keeping municipalities in order of first appearance.
+pub fn parse_csv<T: FromParts, R: io::Read>(reader: R) -> Result<Vec<T>, AppError> {
+    parse_csv_with(reader, Delimiter::Comma)
+}
+
+/// Like [`parse_csv`], with fields separated by `delimiter`.
+pub fn parse_csv_with<T: FromParts, R: io::Read>(
+    reader: R,
+    delimiter: Delimiter,
+) -> Result<Vec<T>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(delimiter.byte())
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut order: Vec<String> = Vec::new();
+    let mut grouped: HashMap<String, HashMap<i32, (i32, f64)>> = HashMap::new();
+
+    for (i, row) in reader.deserialize::<CsvRow>().enumerate() {
+        // Row 1 is the header
+        let row = row.map_err(|err| AppError::Parse {
+            line: i + 2,
+            message: err.to_string(),
+        })?;
+        if !grouped.contains_key(&row.municipality) {
+            order.push(row.municipality.clone());
+        }
+        grouped
+            .entry(row.municipality)
+            .or_default()
+            .insert(row.category, (row.year, row.value));
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|municipality| {
+            let data = grouped.remove(&municipality).unwrap_or_default();
+            T::from_parts(municipality, data)
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct CoordinateRow {
+    municipality: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// Read a CSV file with a `municipality,latitude,longitude` header into a
+/// municipality -> (latitude, longitude) map.
+pub fn read_coordinates(file_path: &str) -> Result<HashMap<String, (f64, f64)>, AppError> {
+    let file = open_file(file_path)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut coordinates = HashMap::new();
+    for (i, row) in reader.deserialize::<CoordinateRow>().enumerate() {
+        let row = row.map_err(|err| {
+            AppError::Parse {
+                line: i + 2,
+                message: err.to_string(),
+            }
+            .in_file(file_path)
+        })?;
+        coordinates.insert(row.municipality, (row.latitude, row.longitude));
+    }
+
+    Ok(coordinates)
+}
+
+/// Read a category key file with one `number; name` line per category, such as
+/// `edukey.txt`, into a category -> name map. Blank lines are skipped. A `.json`
+/// file is instead read as an object from category to name, like
+/// `{ "1": "literacy_rate", "2": "enrollment" }`.
+pub fn read_category_names(file_path: &str) -> Result<HashMap<i32, String>, AppError> {
+    if file_path.ends_with(".json") {
+        let reader = io::BufReader::new(open_file(file_path)?);
+        return serde_json::from_reader(reader)
+            .map_err(|err| AppError::from(err).in_file(file_path));
+    }
+    read_key_lines(file_path, "number; name", |name| Ok(name.to_string()))
+}
+
+/// Read a bucket mapping for [`bucketize`] with one `category; bucket` line per
+/// source category, both numbers. Blank lines are skipped.
+pub fn read_bucket_mapping(file_path: &str) -> Result<HashMap<i32, i32>, AppError> {
+    read_key_lines(file_path, "category; bucket", |bucket| {
+        bucket.parse().map_err(|_| "bucket is not a number")
+    })
+}
+
+// Parse `category; value` lines, turning the trimmed value into a `V` with `parse`;
+// `format` describes a line for error messages
+fn read_key_lines<V>(
+    file_path: &str,
+    format: &str,
+    parse: impl Fn(&str) -> Result<V, &'static str>,
+) -> Result<HashMap<i32, V>, AppError> {
+    let reader = io::BufReader::new(open_file(file_path)?);
+
+    let mut values = HashMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parse_error = |message: &str| {
+            AppError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            }
+            .in_file(file_path)
+        };
+        let (category, value) = line
+            .split_once(';')
+            .ok_or_else(|| parse_error(&format!("expected '{}'", format)))?;
+        let category = category
+            .trim()
+            .parse()
+            .map_err(|_| parse_error("category is not a number"))?;
+        values.insert(category, parse(value.trim()).map_err(parse_error)?);
+    }
+
+    Ok(values)
+}
+
+/// Restrict every record to the categories measured in `year`, dropping records
+/// that have no such category.
+pub fn filter_by_year<T: GraphData + FromParts>(data: &[T], year: i32) -> Vec<T> {
+    data.iter()
+        .filter_map(|entry| {
+            let kept: HashMap<i32, (i32, f64)> = entry
+                .categories()
+                .into_iter()
+                .filter(|&category| entry.get_year(category) == Some(year))
+                .filter_map(|category| Some((category, (year, entry.get_weight(category)?))))
+                .collect();
+            if kept.is_empty() {
+                None
+            } else {
+                Some(T::from_parts(entry.municipality().to_string(), kept))
+            }
+        })
+        .collect()
+}
+
+/// Drop the records of the `municipalities` named (ignoring case) and the
+/// `categories` listed from every other record, dropping records left with no
+/// category.
+pub fn exclude<T: GraphData + FromParts>(
+    data: &[T],
+    municipalities: &[String],
+    categories: &[i32],
+) -> Vec<T> {
+    let municipalities: HashSet<String> = municipalities
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+    data.iter()
+        .filter(|entry| !municipalities.contains(&entry.municipality().to_lowercase()))
+        .filter_map(|entry| {
+            let kept: HashMap<i32, (i32, f64)> = entry
+                .categories()
+                .into_iter()
+                .filter(|category| !categories.contains(category))
+                .filter_map(|category| {
+                    Some((
+                        category,
+                        (entry.get_year(category)?, entry.get_weight(category)?),
+                    ))
+                })
+                .collect();
+            if kept.is_empty() {
+                None
+            } else {
+                Some(T::from_parts(entry.municipality().to_string(), kept))
+            }
+        })
+        .collect()
+}
+
+/// How [`bucketize`] combines the weights of categories in the same bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Add them up, for counts.
+    Sum,
+    /// Average them, for shares and rates.
+    Mean,
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "sum" => Ok(Aggregate::Sum),
+            "mean" => Ok(Aggregate::Mean),
+            _ => Err(format!(
+                "unknown aggregate '{}', expected sum or mean",
+                value
+            )),
+        }
+    }
+}
+
+/// Collapse categories into coarser buckets: every category that `mapping`
+/// sends to the same bucket becomes one category numbered after the bucket,
+/// whose weight combines theirs as `aggregate` says and whose year is the
+/// latest of theirs. Categories missing from `mapping` are dropped, as are
+/// records left with no categories.
+pub fn bucketize<T: GraphData + FromParts>(
+    data: &[T],
+    mapping: &HashMap<i32, i32>,
+    aggregate: Aggregate,
+) -> Vec<T> {
+    data.iter()
+        .filter_map(|entry| {
+            let mut buckets: HashMap<i32, (i32, f64, usize)> = HashMap::new();
+            for category in entry.categories() {
+                let (Some(&bucket), Some(weight), Some(year)) = (
+                    mapping.get(&category),
+                    entry.get_weight(category),
+                    entry.get_year(category),
+                ) else {
+                    continue;
+                };
+                let (latest, total, count) = buckets.entry(bucket).or_insert((year, 0.0, 0));
+                *latest = (*latest).max(year);
+                *total += weight;
+                *count += 1;
+            }
+            if buckets.is_empty() {
+                return None;
+            }
+
+            let data = buckets
+                .into_iter()
+                .map(|(bucket, (year, total, count))| {
+                    let weight = match aggregate {
+                        Aggregate::Sum => total,
+                        Aggregate::Mean => total / count as f64,
+                    };
+                    (bucket, (year, weight))
+                })
+                .collect();
+            Some(T::from_parts(entry.municipality().to_string(), data))
+        })
+        .collect()
+}
+
+/// Check that every weight of `categories` lies within `range`. On failure,
+/// lists every `(municipality, category, value)` outside it, in input order.
+pub fn validate<T: GraphData>(
+    data: &[T],
+    categories: &[i32],
+    range: RangeInclusive<f64>,
+) -> Result<(), Vec<(String, i32, f64)>> {
+    let mut invalid = Vec::new();
+    for entry in data {
+        for &category in categories {
+            if let Some(value) = entry.get_weight(category) {
+                if !range.contains(&value) {
+                    invalid.push((entry.municipality().to_string(), category, value));
+                }
+            }
+        }
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+/// The record of the municipality called `name`, ignoring case. When several
+/// records match, the first one is returned.
+pub fn find_by_municipality<'a, T: GraphData>(data: &'a [T], name: &str) -> Option<&'a T> {
+    let name = name.to_lowercase();
+    data.iter()
+        .find(|entry| entry.municipality().to_lowercase() == name)
+}
+
+/// Index the records by lowercased municipality name, for data that is looked
+/// up many times. Each name maps to the position of its first record.
+pub fn municipality_index<T: GraphData>(data: &[T]) -> HashMap<String, usize> {
+    let mut index = HashMap::new();
+    for (i, entry) in data.iter().enumerate() {
+        index
+            .entry(entry.municipality().to_lowercase())
+            .or_insert(i);
+    }
+    index
+}
+
+/// The sorted union of the categories present in any record.
+pub fn all_categories<T: GraphData>(data: &[T]) -> Vec<i32> {
+    let categories: BTreeSet<i32> = data.iter().flat_map(|entry| entry.categories()).collect();
+    categories.into_iter().collect()
+}
+
+/// How many records have each category, sorted by category.
+pub fn category_counts<T: GraphData>(data: &[T]) -> BTreeMap<i32, usize> {
+    let mut counts = BTreeMap::new();
+    for category in data.iter().flat_map(|entry| entry.categories()) {
+        *counts.entry(category).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// What to do with several records for the same municipality in one dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep the first record.
+    First,
+    /// Keep the last record.
+    Last,
+    /// Combine the categories of all records; later records win on the categories they share.
+    Merge,
+    /// Combine the categories of all records, averaging the weights of the categories
+    /// they share and keeping the latest year.
+    Mean,
+    /// Fail, listing the duplicated names.
+    Error,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "first" => Ok(DuplicatePolicy::First),
+            "last" => Ok(DuplicatePolicy::Last),
+            "merge" => Ok(DuplicatePolicy::Merge),
+            "mean" => Ok(DuplicatePolicy::Mean),
+            "error" => Ok(DuplicatePolicy::Error),
+            _ => Err(format!(
+                "unknown duplicate policy '{}', expected first, last, merge, mean or error",
+                value
+            )),
+        }
+    }
+}
+
+/// Collapse records sharing a municipality name into one according to `policy`.
+/// Municipalities keep the position of their first record.
+pub fn deduplicate<T: GraphData + FromParts>(
+    data: Vec<T>,
+    policy: DuplicatePolicy,
+) -> Result<Vec<T>, AppError> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<T>> = HashMap::new();
+    for entry in data {
+        let name = entry.municipality().to_string();
+        if !groups.contains_key(&name) {
+            order.push(name.clone());
+        }
+        groups.entry(name).or_default().push(entry);
+    }
+
+    if policy == DuplicatePolicy::Error {
+        let duplicates: Vec<String> = order
+            .iter()
+            .filter(|name| groups[*name].len() > 1)
+            .cloned()
+            .collect();
+        if !duplicates.is_empty() {
+            return Err(AppError::DuplicateMunicipalities(duplicates));
+        }
+    }
+
+    let mut deduplicated = Vec::with_capacity(order.len());
+    for name in order {
+        let mut records = groups.remove(&name).unwrap_or_default();
+        let record = match policy {
+            DuplicatePolicy::First | DuplicatePolicy::Error => records.swap_remove(0),
+            DuplicatePolicy::Last => records.pop().expect("every group has a record"),
+            DuplicatePolicy::Merge | DuplicatePolicy::Mean => {
+                let mut values: HashMap<i32, Vec<(i32, f64)>> = HashMap::new();
+                for record in &records {
+                    for category in record.categories() {
+                        if let (Some(year), Some(weight)) =
+                            (record.get_year(category), record.get_weight(category))
+                        {
+                            values.entry(category).or_default().push((year, weight));
+                        }
+                    }
+                }
+                let merged = values
+                    .into_iter()
+                    .map(|(category, values)| {
+                        let value = if policy == DuplicatePolicy::Mean {
+                            let year = values.iter().map(|&(year, _)| year).max();
+                            let total: f64 = values.iter().map(|&(_, weight)| weight).sum();
+                            (
+                                year.expect("every category has a value"),
+                                total / values.len() as f64,
+                            )
+                        } else {
+                            values[values.len() - 1]
+                        };
+                        (category, value)
+                    })
+                    .collect();
+                T::from_parts(name, merged)
+            }
+        };
+        deduplicated.push(record);
+    }
+
+    Ok(deduplicated)
+}
+
+/// What to do with `NaN` and infinite weights, which would make every distance
+/// they enter `NaN` and silently corrupt clustering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Fail, listing the values.
+    Error,
+    /// Replace them with `0.0`, like a missing category.
+    Zero,
+}
+
+impl FromStr for NonFinitePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "error" => Ok(NonFinitePolicy::Error),
+            "zero" => Ok(NonFinitePolicy::Zero),
+            _ => Err(format!(
+                "unknown non-finite policy '{}', expected error or zero",
+                value
+            )),
+        }
+    }
+}
+
+/// Deal with the `NaN` and infinite weights in `data` according to `policy`.
+/// Under [`NonFinitePolicy::Error`] this fails with [`AppError::NonFinite`],
+/// listing every offending value in input order.
+pub fn handle_non_finite<T: GraphData + FromParts>(
+    data: Vec<T>,
+    policy: NonFinitePolicy,
+) -> Result<Vec<T>, AppError> {
+    let non_finite: Vec<(String, i32, f64)> = data
+        .iter()
+        .flat_map(|entry| {
+            entry.categories().into_iter().filter_map(move |category| {
+                let value = entry.get_weight(category)?;
+                (!value.is_finite()).then(|| (entry.municipality().to_string(), category, value))
+            })
+        })
+        .collect();
+    if non_finite.is_empty() {
+        return Ok(data);
+    }
+
+    match policy {
+        NonFinitePolicy::Error => Err(AppError::NonFinite(non_finite)),
+        NonFinitePolicy::Zero => {
+            warn!("replacing {} non-finite values with 0", non_finite.len());
+            Ok(data
+                .into_iter()
+                .map(|entry| {
+                    let parts = entry
+                        .categories()
+                        .into_iter()
+                        .filter_map(|category| {
+                            let year = entry.get_year(category)?;
+                            let value = entry.get_weight(category)?;
+                            let value = if value.is_finite() { value } else { 0.0 };
+                            Some((category, (year, value)))
+                        })
+                        .collect();
+                    T::from_parts(entry.municipality().to_string(), parts)
+                })
+                .collect())
+        }
+    }
+}
+
+/// How municipality names are compared when joining datasets. The original
+/// names are always kept for display; only the comparison key is normalized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NameNormalization {
+    /// Ignore leading and trailing whitespace.
+    pub trim: bool,
+    /// Ignore case.
+    pub lowercase: bool,
+    /// Ignore accents and other combining marks, so "São" matches "Sao".
+    pub strip_diacritics: bool,
+}
+
+impl NameNormalization {
+    /// Compare names exactly as written.
+    pub const EXACT: NameNormalization = NameNormalization {
+        trim: false,
+        lowercase: false,
+        strip_diacritics: false,
+    };
+
+    /// Trim, lowercase and strip diacritics.
+    pub const LOOSE: NameNormalization = NameNormalization {
+        trim: true,
+        lowercase: true,
+        strip_diacritics: true,
+    };
+
+    /// The comparison key for `name`.
+    pub fn apply(&self, name: &str) -> String {
+        let mut key = if self.trim { name.trim() } else { name }.to_string();
+        if self.strip_diacritics {
+            key = key.nfd().filter(|c| !is_combining_mark(*c)).collect();
+        }
+        if self.lowercase {
+            key = key.to_lowercase();
+        }
+        key
+    }
+}
+
+/// Trim, lowercase and strip diacritics from a municipality name.
+pub fn normalize_name(name: &str) -> String {
+    NameNormalization::LOOSE.apply(name)
+}
+
+/// Pair up the records of the two datasets that describe the same municipality.
+pub fn filter_common_municipalities<A: GraphData + Clone, B: GraphData + Clone>(
+    data1: &[A],
+    data2: &[B],
+) -> Vec<(String, A, B)> {
+    filter_common_municipalities_with(data1, data2, NameNormalization::EXACT)
+}
+
+/// Like `filter_common_municipalities`, but comparing names after applying
+/// `normalization`. The returned name is the one from `data1`.
+pub fn filter_common_municipalities_with<A: GraphData + Clone, B: GraphData + Clone>(
+    data1: &[A],
+    data2: &[B],
+    normalization: NameNormalization,
+) -> Vec<(String, A, B)> {
+    let mut common_municipalities = Vec::new();
+    let mut index: HashMap<String, &B> = HashMap::new();
+    for entry2 in data2 {
+        index
+            .entry(normalization.apply(entry2.municipality()))
+            .or_insert(entry2);
+    }
+
+    for entry1 in data1.iter() {
+        if let Some(entry2) = index.get(&normalization.apply(entry1.municipality())) {
+            common_municipalities.push((
+                entry1.municipality().to_string(),
+                entry1.clone(),
+                (*entry2).clone(),
+            ));
+        }
+    }
+
+    common_municipalities
+}
+
+/// Restrict both datasets to the municipalities they share, compared after
+/// `normalization`, in the order of `data1`. The `data2` records are renamed to
+/// the `data1` spelling, so both datasets name every municipality alike.
+pub fn restrict_to_common<A, B>(
+    data1: &[A],
+    data2: &[B],
+    normalization: NameNormalization,
+) -> (Vec<A>, Vec<B>)
+where
+    A: GraphData + Clone,
+    B: GraphData + FromParts + Clone,
+{
+    filter_common_municipalities_with(data1, data2, normalization)
+        .into_iter()
+        .map(|(municipality, entry1, entry2)| {
+            let parts = entry2
+                .categories()
+                .into_iter()
+                .filter_map(|category| {
+                    Some((
+                        category,
+                        (entry2.get_year(category)?, entry2.get_weight(category)?),
+                    ))
+                })
+                .collect();
+            (entry1, B::from_parts(municipality, parts))
+        })
+        .unzip()
+}
+
+/// Names of the municipalities in `a` that don't appear in `b`, in the order of `a`.
+pub fn only_in_first<A: GraphData, B: GraphData>(a: &[A], b: &[B]) -> Vec<String> {
+    only_in_first_with(a, b, NameNormalization::EXACT)
+}
+
+/// Names of the municipalities in `b` that don't appear in `a`, in the order of `b`.
+pub fn only_in_second<A: GraphData, B: GraphData>(a: &[A], b: &[B]) -> Vec<String> {
+    only_in_first(b, a)
+}
+
+/// Like `only_in_first`, but comparing names after applying `normalization`.
+pub fn only_in_first_with<A: GraphData, B: GraphData>(
+    a: &[A],
+    b: &[B],
+    normalization: NameNormalization,
+) -> Vec<String> {
+    let names: HashSet<String> = b
+        .iter()
+        .map(|entry| normalization.apply(entry.municipality()))
+        .collect();
+    a.iter()
+        .map(|entry| entry.municipality())
+        .filter(|name| !names.contains(&normalization.apply(name)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Like `only_in_second`, but comparing names after applying `normalization`.
+pub fn only_in_second_with<A: GraphData, B: GraphData>(
+    a: &[A],
+    b: &[B],
+    normalization: NameNormalization,
+) -> Vec<String> {
+    only_in_first_with(b, a, normalization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixture, temp_file, temp_path};
+    use crate::{k_means_clustering, KMeansConfig};
+
+    #[test]
+    fn common_municipalities_are_matched_by_name() {
+        let mut education = Vec::new();
+        for (name, value) in [
+            ("Shared_A", 10.0),
+            ("Education_Only", 20.0),
+            ("Shared_B", 30.0),
+        ] {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, value);
+            education.push(entry);
+        }
+        // The shared municipalities have other values here, so whole records differ
+        let mut pop_growth = Vec::new();
+        for (name, value) in [
+            ("Shared_B", 0.5),
+            ("Pop_Growth_Only", 1.5),
+            ("Shared_A", 2.5),
+        ] {
+            let mut entry = PopGrowthData::new(name);
+            entry.set(1, 2021, value);
+            pop_growth.push(entry);
+        }
+
+        let common = filter_common_municipalities(&education, &pop_growth);
+        let names: Vec<&str> = common.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["Shared_A", "Shared_B"]);
+        assert_eq!(common[0].1.get_weight(1), Some(10.0));
+        assert_eq!(common[0].2.get_weight(1), Some(2.5));
+
+        assert!(filter_common_municipalities(&education[1..2], &pop_growth).is_empty());
+    }
+
+    #[test]
+    fn csv_rows_are_grouped_by_municipality() {
+        let csv = "municipality,year,category,value\n\
+                   Alpha,2020,1,95.5\n\
+                   Beta,2020,1,80.0\n\
+                   Alpha, 2021, 2, 12.25\n";
+        let records: Vec<EducationData> = parse_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].municipality(), "Alpha");
+        assert_eq!(records[0].categories(), [1, 2]);
+        assert_eq!(records[0].get_weight(1), Some(95.5));
+        assert_eq!(records[0].get_year(2), Some(2021));
+        assert_eq!(records[0].get_weight(2), Some(12.25));
+        assert_eq!(records[1].municipality(), "Beta");
+
+        let err = parse_csv::<EducationData, _>(
+            "municipality,year,category,value\nAlpha,x,1,2\n".as_bytes(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::Parse { line: 2, .. }));
+    }
+
+    #[test]
+    fn ndjson_has_one_record_per_line() {
+        let ndjson = r#"{"municipality": "Alpha", "data": {"1": [2020, 95.5]}}
+    {"municipality": "Beta", "data": {"1": [2020, 80.0], "2": [2021, 3.0]}}
+
+    {"municipality": "Gamma", "data": {}}
+    "#;
+        let records: Vec<PopGrowthData> = parse_ndjson(ndjson.as_bytes()).unwrap();
+        let names: Vec<&str> = records.iter().map(|record| record.municipality()).collect();
+        assert_eq!(names, ["Alpha", "Beta", "Gamma"]);
+        assert_eq!(records[1].get_year(2), Some(2021));
+        assert!(records[2].categories().is_empty());
+
+        let err = parse_ndjson::<PopGrowthData, _>("{}\n{\"municipality\"".as_bytes()).unwrap_err();
+        assert!(matches!(err, AppError::Parse { line: 1, .. }));
+    }
+
+    #[test]
+    fn filtering_by_year_drops_other_years() {
+        let mut mixed = EducationData::new("Mixed");
+        mixed.set(1, 2019, 1.0);
+        mixed.set(2, 2020, 2.0);
+        mixed.set(3, 2021, 3.0);
+        let mut old = EducationData::new("Old");
+        old.set(1, 2018, 4.0);
+
+        let filtered = filter_by_year(&[mixed, old], 2020);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].municipality(), "Mixed");
+        assert_eq!(filtered[0].categories(), [2]);
+        assert_eq!(filtered[0].get_weight(2), Some(2.0));
+    }
+
+    #[test]
+    fn municipalities_in_only_one_dataset_are_listed_in_order() {
+        let education: Vec<EducationData> = ["Shared", "Edu_B", "Edu_A"]
+            .into_iter()
+            .map(EducationData::new)
+            .collect();
+        let pop_growth: Vec<PopGrowthData> = ["Pop_A", "shared", "Shared"]
+            .into_iter()
+            .map(PopGrowthData::new)
+            .collect();
+
+        assert_eq!(only_in_first(&education, &pop_growth), ["Edu_B", "Edu_A"]);
+        assert_eq!(only_in_second(&education, &pop_growth), ["Pop_A", "shared"]);
+        assert_eq!(
+            only_in_second_with(&education, &pop_growth, NameNormalization::LOOSE),
+            ["Pop_A"]
+        );
+        assert!(only_in_first(&education, &education).is_empty());
+    }
+
+    #[test]
+    fn loose_names_join_despite_case_spacing_and_accents() {
+        assert_eq!(normalize_name(" Sao Paulo "), "sao paulo");
+        assert_eq!(normalize_name("São Paulo"), "sao paulo");
+
+        let education = [
+            EducationData::new(" Sao Paulo "),
+            EducationData::new("Curitiba"),
+        ];
+        let pop_growth = [
+            PopGrowthData::new("sao paulo"),
+            PopGrowthData::new("CURITIBA"),
+        ];
+        let common =
+            filter_common_municipalities_with(&education, &pop_growth, NameNormalization::LOOSE);
+        let names: Vec<&str> = common.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, [" Sao Paulo ", "Curitiba"]);
+        assert!(filter_common_municipalities(&education, &pop_growth).is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_collapsed_by_each_policy() {
+        let records = || {
+            let mut first = EducationData::new("Alpha");
+            first.set(1, 2020, 1.0);
+            first.set(2, 2020, 2.0);
+            let mut other = EducationData::new("Beta");
+            other.set(1, 2020, 9.0);
+            let mut last = EducationData::new("Alpha");
+            last.set(2, 2021, 20.0);
+            last.set(3, 2021, 30.0);
+            vec![first, other, last]
+        };
+        let weights = |record: &EducationData| -> Vec<Option<f64>> {
+            (1..=3)
+                .map(|category| record.get_weight(category))
+                .collect()
+        };
+
+        let first = deduplicate(records(), DuplicatePolicy::First).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].municipality(), "Alpha");
+        assert_eq!(first[1].municipality(), "Beta");
+        assert_eq!(weights(&first[0]), [Some(1.0), Some(2.0), None]);
+
+        let last = deduplicate(records(), DuplicatePolicy::Last).unwrap();
+        assert_eq!(last[0].municipality(), "Alpha");
+        assert_eq!(weights(&last[0]), [None, Some(20.0), Some(30.0)]);
+
+        let merged = deduplicate(records(), DuplicatePolicy::Merge).unwrap();
+        assert_eq!(weights(&merged[0]), [Some(1.0), Some(20.0), Some(30.0)]);
+        assert_eq!(merged[0].get_year(2), Some(2021));
+
+        // Category 2 is in both Alpha records, 2.0 in 2020 and 20.0 in 2021
+        let mean = deduplicate(records(), DuplicatePolicy::Mean).unwrap();
+        assert_eq!(mean.len(), 2);
+        assert_eq!(weights(&mean[0]), [Some(1.0), Some(11.0), Some(30.0)]);
+        assert_eq!(mean[0].get_year(2), Some(2021));
+        assert_eq!(weights(&mean[1]), [Some(9.0), None, None]);
+
+        match deduplicate(records(), DuplicatePolicy::Error) {
+            Err(AppError::DuplicateMunicipalities(names)) => assert_eq!(names, ["Alpha"]),
+            other => panic!("expected a duplicate error, got {:?}", other),
+        }
+        assert_eq!(
+            deduplicate(records()[..2].to_vec(), DuplicatePolicy::Error)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn gzipped_json_is_decompressed_on_read() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let json = r#"[{"municipality": "Alpha", "data": {"1": [2020, 95.5]}}]"#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes()).unwrap();
+        let path = temp_path("education.json.gz");
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let read: Vec<EducationData> = read_data(&path).unwrap();
+        let input: Vec<EducationData> = read_input(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for records in [read, input] {
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].municipality(), "Alpha");
+            assert_eq!(records[0].get_weight(1), Some(95.5));
+        }
+    }
+
+    #[test]
+    fn values_outside_the_range_are_reported() {
+        let mut data = Vec::new();
+        for (name, value) in [("Alpha", 95.0), ("Typo", 950.0), ("Gamma", 0.0)] {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, value);
+            entry.set(2, 2020, 50.0);
+            data.push(entry);
+        }
+
+        assert_eq!(
+            validate(&data, &[1, 2], 0.0..=100.0),
+            Err(vec![("Typo".to_string(), 1, 950.0)])
+        );
+        // Only the categories asked for are checked
+        assert_eq!(validate(&data, &[2], 0.0..=100.0), Ok(()));
+    }
+
+    #[test]
+    fn categories_in_one_bucket_are_summed() {
+        let mut entry = EducationData::new("Alpha");
+        entry.set(1, 2019, 10.0);
+        entry.set(2, 2020, 5.5);
+        entry.set(3, 2020, 1.0);
+        // A record with only an unmapped category is dropped
+        let mut unmapped = EducationData::new("Unmapped");
+        unmapped.set(3, 2020, 1.0);
+        let mapping = HashMap::from([(1, 100), (2, 100)]);
+
+        let bucketed = bucketize(&[entry, unmapped], &mapping, Aggregate::Sum);
+        assert_eq!(bucketed.len(), 1);
+        assert_eq!(bucketed[0].categories(), [100]);
+        assert_eq!(bucketed[0].get_weight(100), Some(15.5));
+        assert_eq!(bucketed[0].get_year(100), Some(2020));
+    }
+
+    #[test]
+    fn json_is_parsed_from_an_in_memory_reader_as_from_stdin() {
+        let json = r#"[
+            {"municipality": "Alpha", "data": {"1": [2020, 95.5]}},
+            {"municipality": "Beta", "data": {"1": [2020, 80.0], "2": [2021, 3.0]}}
+        ]"#;
+        let records: Vec<PopGrowthData> = parse_json(io::Cursor::new(json)).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].municipality(), "Beta");
+        assert_eq!(records[1].get_weight(2), Some(3.0));
+        assert_eq!(records[1].get_year(2), Some(2021));
+        assert!(parse_json::<PopGrowthData, _>("not json".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn nan_weights_are_rejected_or_zeroed() {
+        let records = || {
+            let mut data = Vec::new();
+            for (name, value) in [("Alpha", 1.0), ("Broken", f64::NAN), ("Gamma", 3.0)] {
+                let mut entry = EducationData::new(name);
+                entry.set(1, 2020, value);
+                entry.set(2, 2020, 4.0);
+                data.push(entry);
+            }
+            data
+        };
+
+        match handle_non_finite(records(), NonFinitePolicy::Error) {
+            Err(AppError::NonFinite(values)) => {
+                assert_eq!(values.len(), 1);
+                assert_eq!((values[0].0.as_str(), values[0].1), ("Broken", 1));
+                assert!(values[0].2.is_nan());
+            }
+            other => panic!("expected a non-finite error, got {:?}", other),
+        }
+
+        let zeroed = handle_non_finite(records(), NonFinitePolicy::Zero).unwrap();
+        assert_eq!(zeroed[1].get_weight(1), Some(0.0));
+        assert_eq!(zeroed[1].get_weight(2), Some(4.0));
+        assert_eq!(zeroed[2].get_weight(1), Some(3.0));
+    }
+
+    #[test]
+    fn tsv_rows_are_split_on_tabs() {
+        let tsv = "municipality\tyear\tcategory\tvalue\n\
+                   Alpha, North\t2020\t1\t95.5\n\
+                   Beta\t2021\t2\t3.25\n";
+        let records: Vec<EducationData> = parse_csv_with(tsv.as_bytes(), Delimiter::Tab).unwrap();
+
+        assert_eq!(records.len(), 2);
+        // A comma is part of the name, not a separator
+        assert_eq!(records[0].municipality(), "Alpha, North");
+        assert_eq!(records[0].get_weight(1), Some(95.5));
+        assert_eq!(records[1].get_year(2), Some(2021));
+        assert_eq!(records[1].get_weight(2), Some(3.25));
+        assert_eq!(Delimiter::from_extension("input.tsv"), Some(Delimiter::Tab));
+    }
+
+    #[test]
+    fn excluded_municipalities_and_categories_never_reach_the_clusters() {
+        let mut data = Vec::new();
+        for (i, name) in ["Alpha", "Beta", "Skipped", "Gamma"].iter().enumerate() {
+            let mut entry = EducationData::new(*name);
+            entry.set(1, 2020, i as f64);
+            entry.set(2, 2020, 10.0 * i as f64);
+            entry.set(3, 2020, 1.0);
+            data.push(entry);
+        }
+        // Records left with no category go too
+        let mut only_excluded = EducationData::new("Only_Category_3");
+        only_excluded.set(3, 2020, 5.0);
+        data.push(only_excluded);
+
+        let kept = exclude(&data, &["skipped".to_string()], &[3]);
+        let categories = all_categories(&kept);
+        assert_eq!(categories, [1, 2]);
+
+        let result =
+            k_means_clustering(&kept, &categories, &[], true, &KMeansConfig::new(2)).unwrap();
+        let mut members: Vec<&str> = result
+            .assignments
+            .values()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        members.sort_unstable();
+        assert_eq!(members, ["Alpha", "Beta", "Gamma"]);
+        assert!(result.centroids.iter().all(|centroid| centroid.len() == 2));
+    }
+
+    #[test]
+    fn ndjson_files_are_read_one_record_at_a_time() {
+        let path = temp_path("lazy.ndjson");
+        std::fs::write(
+            &path,
+            "{\"municipality\": \"Alpha\", \"data\": {\"1\": [2020, 1.0]}}\nnot json\n",
+        )
+        .unwrap();
+
+        // The first record is there before the bad line is read
+        let mut records = read_data_iter::<EducationData>(&path);
+        assert_eq!(records.next().unwrap().unwrap().municipality(), "Alpha");
+        assert!(matches!(
+            records.next(),
+            Some(Err(AppError::Malformed { source, .. }))
+                if matches!(*source, AppError::Parse { line: 2, .. })
+        ));
+        assert!(records.next().is_none());
+
+        // Reading the file by its extension goes through the same records
+        let err = read_input::<EducationData>(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "could not parse '{}': line 2: expected ident at line 1 column 2",
+                path
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let mut missing = read_data_iter::<EducationData>(&temp_path("missing.ndjson"));
+        assert!(matches!(missing.next(), Some(Err(AppError::Open { .. }))));
+        assert!(missing.next().is_none());
+    }
+
+    #[test]
+    fn reading_disjoint_files_yields_the_union() {
+        let paths = [fixture("education.json"), fixture("education_gamma.json")];
+        let education: Vec<EducationData> =
+            read_inputs(&paths, None, DuplicatePolicy::Error).unwrap();
+        let names: Vec<&str> = education.iter().map(|entry| entry.municipality()).collect();
+        assert_eq!(
+            names,
+            [
+                "Alpha_North",
+                "Alpha_South",
+                "Alpha_East",
+                "Beta_North",
+                "Beta_South",
+                "Education_Only",
+                "Gamma_North",
+                "Gamma_South",
+            ]
+        );
+    }
+
+    #[test]
+    fn reading_overlapping_files_follows_duplicate_policy() {
+        let paths = [fixture("education.json"), fixture("education_update.json")];
+        let read =
+            |policy| -> Result<Vec<EducationData>, AppError> { read_inputs(&paths, None, policy) };
+        let alpha_north = |data: &[EducationData]| {
+            let entry = &data[0];
+            assert_eq!(entry.municipality(), "Alpha_North");
+            assert_eq!(data.len(), 6);
+            (
+                entry.get_weight(1),
+                entry.get_weight(2),
+                entry.get_weight(3),
+            )
+        };
+
+        assert!(matches!(
+            read(DuplicatePolicy::Error),
+            Err(AppError::DuplicateMunicipalities(names)) if names == ["Alpha_North"]
+        ));
+        assert_eq!(
+            alpha_north(&read(DuplicatePolicy::First).unwrap()),
+            (Some(10.0), Some(10.0), None)
+        );
+        assert_eq!(
+            alpha_north(&read(DuplicatePolicy::Last).unwrap()),
+            (None, Some(12.0), Some(7.5))
+        );
+        assert_eq!(
+            alpha_north(&read(DuplicatePolicy::Merge).unwrap()),
+            (Some(10.0), Some(12.0), Some(7.5))
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_file() {
+        // A semicolon-separated text file is neither JSON nor comma-separated
+        let text = temp_file(
+            "stats.txt",
+            "municipality;year;category;value\nAlpha;2020;1;1.0\n",
+        );
+        let err = read_input::<EducationData>(&text).unwrap_err();
+        assert!(matches!(&err, AppError::Malformed { path, source }
+            if *path == text && matches!(**source, AppError::Json(_))));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("could not parse '{}': ", text)));
+
+        let csv = temp_file(
+            "stats.csv",
+            "municipality,year,category,value\nAlpha,x,1,1.0\n",
+        );
+        let err = read_input::<EducationData>(&csv).unwrap_err();
+        assert!(matches!(&err, AppError::Malformed { path, source }
+            if *path == csv && matches!(**source, AppError::Parse { line: 2, .. })));
+
+        let key = temp_file("key.txt", "1; literacy\nenrollment\n");
+        assert!(matches!(
+            read_category_names(&key),
+            Err(AppError::Malformed { path, .. }) if path == key
+        ));
+        for path in [text, csv, key] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
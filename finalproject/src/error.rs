@@ -0,0 +1,93 @@
+use plotters::drawing::DrawingAreaErrorKind;
+use std::io;
+use thiserror::Error;
+
+/// Everything that can go wrong while reading data or writing results.
+#[derive(Debug, Error)]
+pub enum AppError {
+    /// Reading or writing a file failed.
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    /// An input file could not be opened.
+    #[error("could not open '{path}': {source}")]
+    Open { path: String, source: io::Error },
+    /// A JSON input file is malformed.
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    /// Writing a CSV file failed.
+    #[error("{0}")]
+    Csv(#[from] csv::Error),
+    /// The record on a (1-based) line of an input file could not be parsed.
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+    /// An input file is malformed, with the JSON, CSV or line error saying where.
+    #[error("could not parse '{path}': {source}")]
+    Malformed { path: String, source: Box<AppError> },
+    /// No record has the requested category.
+    #[error("no record has category {0}")]
+    MissingCategory(i32),
+    /// An input file holds no records.
+    #[error("'{0}' has no records")]
+    EmptyDataset(String),
+    /// Clustering was asked for no clusters, or for more clusters than records.
+    #[error("cannot make {k} clusters from {records} records")]
+    ClusterCount { k: usize, records: usize },
+    /// Some municipalities appear in more than one record of a dataset.
+    #[error("duplicate municipalities: {}", .0.join(", "))]
+    DuplicateMunicipalities(Vec<String>),
+    /// Some weights are `NaN` or infinite, as `(municipality, category, value)`.
+    #[error("non-finite values: {}", list_values(.0))]
+    NonFinite(Vec<(String, i32, f64)>),
+    /// Some weights of a dataset fall outside the accepted range, as
+    /// `(municipality, category, value)`.
+    #[error("{dataset} values outside {min}..={max}: {}", list_values(.values))]
+    OutOfRange {
+        dataset: String,
+        min: f64,
+        max: f64,
+        values: Vec<(String, i32, f64)>,
+    },
+    /// The datasets were restricted to their common municipalities, but share none.
+    #[error("the datasets have no municipality in common")]
+    NoCommonMunicipalities,
+    /// No municipality of a dataset has enough of its categories.
+    #[error("no {0} municipality has enough categories")]
+    Incomplete(String),
+    /// Graphviz could not be run or failed to render a graph.
+    #[error("{0}")]
+    Graphviz(String),
+    /// Drawing a plot failed.
+    #[error("could not draw plot: {0}")]
+    Plot(String),
+}
+
+impl AppError {
+    /// Name the input file `path` in a failure to parse it, as
+    /// [`AppError::Malformed`]; any other error is returned as it is.
+    pub fn in_file(self, path: &str) -> Self {
+        match self {
+            AppError::Json(_) | AppError::Csv(_) | AppError::Parse { .. } => AppError::Malformed {
+                path: if path == "-" { "stdin" } else { path }.to_string(),
+                source: Box::new(self),
+            },
+            other => other,
+        }
+    }
+}
+
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for AppError {
+    fn from(err: DrawingAreaErrorKind<E>) -> Self {
+        AppError::Plot(err.to_string())
+    }
+}
+
+// "municipality category N = value" for each value, comma-separated
+fn list_values(values: &[(String, i32, f64)]) -> String {
+    values
+        .iter()
+        .map(|(municipality, category, value)| {
+            format!("{} category {} = {}", municipality, category, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
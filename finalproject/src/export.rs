@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use crate::graph::escape_xml;
+use crate::{axis_range, cluster_by_name, cluster_color, AppError, ClusteringResult, GraphData};
+
+/// One dataset's part of the JSON report: its k-means clustering and the
+/// silhouette score of that clustering.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetReport {
+    /// The clusters, centroids and inertia.
+    pub clustering: ClusteringResult,
+    /// Mean silhouette of `clustering` over its features.
+    pub silhouette: f64,
+    /// Name of each centroid dimension, in order. Empty in reports written
+    /// before the names were recorded.
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+/// Everything `write_report_json` writes: both datasets' clusterings and the
+/// municipalities present in both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    /// The education dataset's clustering.
+    pub education: DatasetReport,
+    /// The population growth dataset's clustering.
+    pub pop_growth: DatasetReport,
+    /// Names found in both datasets, in education order.
+    pub common_municipalities: Vec<String>,
+}
+
+impl Report {
+    /// The report with every number rounded to `precision` decimal places.
+    pub fn rounded(mut self, precision: usize) -> Self {
+        for dataset in [&mut self.education, &mut self.pop_growth] {
+            let clustering = &mut dataset.clustering;
+            for value in clustering.centroids.iter_mut().flatten() {
+                *value = round_f64(*value, precision);
+            }
+            clustering.inertia = round_f64(clustering.inertia, precision);
+            for value in &mut clustering.inertia_history {
+                *value = round_f64(*value, precision);
+            }
+            dataset.silhouette = round_f64(dataset.silhouette, precision);
+        }
+        self
+    }
+}
+
+/// `x` with `precision` decimal places, the format of every number the program
+/// prints or writes as text.
+pub fn fmt_f64(x: f64, precision: usize) -> String {
+    format!("{:.*}", precision, x)
+}
+
+/// `x` rounded to `precision` decimal places, for outputs such as JSON whose
+/// numbers can't carry a fixed number of decimals.
+pub fn round_f64(x: f64, precision: usize) -> f64 {
+    let scale = 10f64.powi(precision.min(i32::MAX as usize) as i32);
+    let rounded = (x * scale).round() / scale;
+    // Very large values or precisions overflow the scaling and are best left alone
+    if rounded.is_finite() {
+        rounded
+    } else {
+        x
+    }
+}
+
+/// Write one `municipality,cluster_id` row per municipality, sorted by cluster
+/// and then by name.
+pub fn write_clusters_csv(
+    clusters: &HashMap<usize, Vec<String>>,
+    path: &str,
+) -> Result<(), AppError> {
+    let mut rows: Vec<(usize, &str)> = clusters
+        .iter()
+        .flat_map(|(&cluster, members)| members.iter().map(move |m| (cluster, m.as_str())))
+        .collect();
+    rows.sort_unstable();
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["municipality", "cluster_id"])?;
+    for (cluster, municipality) in rows {
+        writer.write_record([municipality, &cluster.to_string()])?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Write a distance matrix from `distance_matrix` as CSV, with a header row and
+/// a first column of municipality names, every distance with `precision` decimal places.
+pub fn write_distance_matrix_csv(
+    names: &[String],
+    distances: &[Vec<f64>],
+    precision: usize,
+    path: &str,
+) -> Result<(), AppError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(std::iter::once("municipality").chain(names.iter().map(String::as_str)))?;
+    for (name, row) in names.iter().zip(distances) {
+        writer.write_record(
+            std::iter::once(name.clone())
+                .chain(row.iter().map(|&distance| fmt_f64(distance, precision))),
+        )?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Write the report as pretty-printed JSON, for dashboards and other tools
+/// further down a pipeline.
+pub fn write_report_json(report: &Report, path: &str) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, report)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Read back a report written by [`write_report_json`].
+pub fn read_report_json(path: &str) -> Result<Report, AppError> {
+    let file = File::open(path).map_err(|source| AppError::Open {
+        path: path.to_string(),
+        source,
+    })?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|err| AppError::from(err).in_file(path))
+}
+
+/// Write a self-contained HTML page with an SVG scatter plot of the clusters,
+/// drawing each municipality at its entry in `coordinates` in its cluster's
+/// color. Hovering over a point shows the municipality's name, cluster and
+/// category values, each category named from `names` when it has an entry and
+/// each value with `precision` decimal places. Municipalities without a cluster
+/// or coordinates are left out.
+pub fn write_clusters_html<T: GraphData>(
+    data: &[T],
+    assignments: &HashMap<usize, Vec<String>>,
+    coordinates: &HashMap<String, (f64, f64)>,
+    names: &HashMap<i32, String>,
+    precision: usize,
+    path: &str,
+) -> Result<(), AppError> {
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 600.0;
+    const MARGIN: f64 = 40.0;
+
+    let clusters = cluster_by_name(assignments);
+    let points: Vec<(&T, usize, (f64, f64))> = data
+        .iter()
+        .filter_map(|entry| {
+            let name = entry.municipality();
+            Some((entry, clusters.get(name).copied()?, *coordinates.get(name)?))
+        })
+        .collect();
+    let x_range = axis_range(points.iter().map(|&(_, _, (x, _))| x));
+    let y_range = axis_range(points.iter().map(|&(_, _, (_, y))| y));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Clusters</title>
+<style>
+body {{ font-family: sans-serif; }}
+circle {{ stroke: white; stroke-width: 1; }}
+circle:hover {{ stroke: black; }}
+#tooltip {{ position: absolute; display: none; padding: 4px 8px; white-space: pre;
+  background: rgba(255, 255, 255, 0.9); border: 1px solid black; pointer-events: none; }}
+</style>
+</head>
+<body>
+<svg width=\"{WIDTH}\" height=\"{HEIGHT}\">
+<rect x=\"{MARGIN}\" y=\"{MARGIN}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\"/>",
+        WIDTH - 2.0 * MARGIN,
+        HEIGHT - 2.0 * MARGIN,
+    )?;
+
+    for (entry, cluster, (x, y)) in points {
+        let px =
+            MARGIN + (x - x_range.start) / (x_range.end - x_range.start) * (WIDTH - 2.0 * MARGIN);
+        let py = HEIGHT
+            - MARGIN
+            - (y - y_range.start) / (y_range.end - y_range.start) * (HEIGHT - 2.0 * MARGIN);
+        let mut info = format!("{} (cluster {})", entry.municipality(), cluster);
+        for category in entry.categories() {
+            if let Some(weight) = entry.get_weight(category) {
+                let name = names
+                    .get(&category)
+                    .cloned()
+                    .unwrap_or_else(|| format!("Category {}", category));
+                info.push_str(&format!("\n{}: {}", name, fmt_f64(weight, precision)));
+            }
+        }
+        let color = cluster_color(cluster);
+        writeln!(
+            writer,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"5\" fill=\"rgb({},{},{})\" data-info=\"{}\"/>",
+            px,
+            py,
+            color.0,
+            color.1,
+            color.2,
+            escape_xml(&info).replace('\n', "&#10;"),
+        )?;
+    }
+
+    writeln!(
+        writer,
+        "</svg>
+<div id=\"tooltip\"></div>
+<script>
+const tooltip = document.getElementById(\"tooltip\");
+for (const point of document.querySelectorAll(\"circle\")) {{
+  point.addEventListener(\"mousemove\", event => {{
+    tooltip.textContent = point.dataset.info;
+    tooltip.style.left = (event.pageX + 12) + \"px\";
+    tooltip.style.top = (event.pageY + 12) + \"px\";
+    tooltip.style.display = \"block\";
+  }});
+  point.addEventListener(\"mouseleave\", () => {{ tooltip.style.display = \"none\"; }});
+}}
+</script>
+</body>
+</html>"
+    )?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+    use crate::{k_means_features, KMeansConfig};
+
+    #[test]
+    fn clusters_csv_has_a_row_per_municipality() {
+        let clusters = HashMap::from([
+            (1, vec!["Gamma".to_string()]),
+            (0, vec!["Beta".to_string(), "Alpha".to_string()]),
+        ]);
+        let path = temp_path("clusters.csv");
+        write_clusters_csv(&clusters, &path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut reader = csv::Reader::from_reader(written.as_bytes());
+        let rows: Vec<(String, usize)> = reader.deserialize().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows,
+            [
+                ("Alpha".to_string(), 0),
+                ("Beta".to_string(), 0),
+                ("Gamma".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn json_report_reads_back_as_written() {
+        let municipalities: Vec<String> = ["Alpha", "Beta", "Gamma"].map(String::from).to_vec();
+        let features = vec![vec![0.0, 1.0], vec![0.5, 1.5], vec![9.0, 9.0]];
+        let clustering =
+            k_means_features(&municipalities, &features, &KMeansConfig::new(2)).unwrap();
+        let dataset = |silhouette| DatasetReport {
+            clustering: clustering.clone(),
+            silhouette,
+            categories: vec!["Graduation".to_string(), "Dropout".to_string()],
+        };
+        let report = Report {
+            education: dataset(0.75),
+            pop_growth: dataset(-0.25),
+            common_municipalities: municipalities.clone(),
+        };
+
+        let path = temp_path("report.json");
+        write_report_json(&report, &path).unwrap();
+        let read = read_report_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.common_municipalities, municipalities);
+        assert_eq!(read.education.silhouette, 0.75);
+        assert_eq!(read.pop_growth.silhouette, -0.25);
+        assert_eq!(read.education.categories, report.education.categories);
+        let (written, read) = (&report.education.clustering, &read.education.clustering);
+        assert_eq!(read.assignments, written.assignments);
+        assert_eq!(read.labels, written.labels);
+        assert_eq!(read.centroids, written.centroids);
+        assert_eq!(read.inertia, written.inertia);
+        assert_eq!(read.inertia_history, written.inertia_history);
+    }
+}
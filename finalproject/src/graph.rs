@@ -0,0 +1,1117 @@
+use log::warn;
+use petgraph::algo::{astar, min_spanning_tree};
+use petgraph::data::FromElements;
+use petgraph::dot::{Config, Dot};
+use petgraph::graph::{DiGraph, Graph, NodeIndex, UnGraph};
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{
+    EdgeRef, GraphProp, GraphRef, IntoEdgeReferences, IntoNodeReferences, NodeIndexable,
+};
+use petgraph::{Direction, EdgeType};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::process::Command;
+use std::str::FromStr;
+
+use crate::{
+    cosine_similarity, filter_common_municipalities, pearson, AppError, EducationData, GraphData,
+    PopGrowthData,
+};
+
+/// Write the graph in Graphviz DOT format, as a `digraph` or an undirected
+/// `graph` to match it. Edges are labeled with their weights when
+/// `show_weights` is set and left unlabeled otherwise. Any petgraph graph
+/// reference works, e.g. `&DiGraph`, `&UnGraph` or `&StableGraph`.
+pub fn visualize_graph<G>(graph: G, file_path: &str, show_weights: bool) -> Result<(), AppError>
+where
+    G: GraphRef + IntoNodeReferences + IntoEdgeReferences + NodeIndexable + GraphProp,
+    G::NodeWeight: std::fmt::Debug,
+    G::EdgeWeight: std::fmt::Debug,
+{
+    let mut file = File::create(file_path)?;
+    let config: &[Config] = if show_weights {
+        &[]
+    } else {
+        &[Config::EdgeNoLabel]
+    };
+    let dot = Dot::with_config(graph, config);
+    write!(file, "{:?}", dot)?;
+
+    Ok(())
+}
+
+/// Render a DOT file to PNG with Graphviz's `dot`. When `dot` isn't installed
+/// this prints a warning and does nothing, so the rest of a run can finish.
+pub fn render_dot_to_png(dot_path: &str, png_path: &str) -> Result<(), AppError> {
+    let output = match Command::new("dot")
+        .args(["-Tpng", dot_path, "-o", png_path])
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            warn!(
+                "Graphviz 'dot' not found, skipping rendering of '{}'",
+                dot_path
+            );
+            return Ok(());
+        }
+        Err(err) => {
+            return Err(AppError::Graphviz(format!(
+                "could not run Graphviz 'dot': {}",
+                err
+            )))
+        }
+    };
+
+    if !output.status.success() {
+        return Err(AppError::Graphviz(format!(
+            "Graphviz 'dot' failed on '{}': {}",
+            dot_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Write the graph as GraphML, with node labels in a `label` data element and
+/// edge weights in a `weight` data element.
+pub fn export_graphml<N, T, Ty>(graph: &Graph<N, T, Ty>, file_path: &str) -> Result<(), AppError>
+where
+    N: std::fmt::Display,
+    T: std::fmt::Display,
+    Ty: EdgeType,
+{
+    let mut file = BufWriter::new(File::create(file_path)?);
+
+    writeln!(file, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        file,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        file,
+        r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+    )?;
+    writeln!(
+        file,
+        r#"  <key id="weight" for="edge" attr.name="weight" attr.type="double"/>"#
+    )?;
+    let edge_default = if graph.is_directed() {
+        "directed"
+    } else {
+        "undirected"
+    };
+    writeln!(file, r#"  <graph id="G" edgedefault="{}">"#, edge_default)?;
+
+    for node in graph.node_indices() {
+        writeln!(
+            file,
+            r#"    <node id="n{}"><data key="label">{}</data></node>"#,
+            node.index(),
+            escape_xml(&graph[node].to_string())
+        )?;
+    }
+    for edge in graph.edge_references() {
+        writeln!(
+            file,
+            r#"    <edge source="n{}" target="n{}"><data key="weight">{}</data></edge>"#,
+            edge.source().index(),
+            edge.target().index(),
+            escape_xml(&edge.weight().to_string())
+        )?;
+    }
+
+    writeln!(file, "  </graph>")?;
+    writeln!(file, "</graphml>")?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Write the graph as an edge list, one `source\ttarget\tweight` line per edge
+/// using the node labels as names. Nodes without any edge get a line holding
+/// only their name, so [`load_graph_edgelist`] brings them back too. An
+/// undirected graph has each edge written once.
+pub fn save_graph_edgelist<N, Ty>(
+    graph: &Graph<N, f64, Ty>,
+    file_path: &str,
+) -> Result<(), AppError>
+where
+    N: std::fmt::Display,
+    Ty: EdgeType,
+{
+    let mut file = BufWriter::new(File::create(file_path)?);
+
+    for edge in graph.edge_references() {
+        writeln!(
+            file,
+            "{}\t{}\t{}",
+            graph[edge.source()],
+            graph[edge.target()],
+            edge.weight()
+        )?;
+    }
+    for node in graph.node_indices() {
+        if graph.neighbors_undirected(node).next().is_none() {
+            writeln!(file, "{}", graph[node])?;
+        }
+    }
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Read a graph written by [`save_graph_edgelist`]. Each name becomes one node,
+/// however many lines it is on, and nodes are added in the order their names
+/// first appear. Blank lines are skipped.
+pub fn load_graph_edgelist(file_path: &str) -> Result<DiGraph<String, f64>, AppError> {
+    let file = File::open(file_path).map_err(|source| AppError::Open {
+        path: file_path.to_string(),
+        source,
+    })?;
+
+    let mut graph = DiGraph::new();
+    let mut nodes: HashMap<String, NodeIndex> = HashMap::new();
+    let mut node = |graph: &mut DiGraph<String, f64>, name: &str| {
+        *nodes
+            .entry(name.to_string())
+            .or_insert_with(|| graph.add_node(name.to_string()))
+    };
+    for (i, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parse_error = |message: &str| {
+            AppError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            }
+            .in_file(file_path)
+        };
+        match line.split('\t').collect::<Vec<_>>()[..] {
+            [name] => {
+                node(&mut graph, name);
+            }
+            [source, target, weight] => {
+                let weight = weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| parse_error("weight is not a number"))?;
+                let source = node(&mut graph, source);
+                let target = node(&mut graph, target);
+                graph.add_edge(source, target, weight);
+            }
+            _ => return Err(parse_error("expected 'source<TAB>target<TAB>weight'")),
+        }
+    }
+
+    Ok(graph)
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// How the similarity graphs compare two municipalities, over the weights of the
+/// categories they share. The first two are distances, so pairs at most the
+/// threshold apart are linked; the others grow with similarity, so pairs at least
+/// the threshold alike are linked. Either way the value is the edge weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Similarity {
+    /// Mean absolute difference of the weights.
+    MeanDifference,
+    /// Euclidean distance between the weight vectors.
+    Euclidean,
+    /// Cosine of the angle between the weight vectors, which compares the shape
+    /// of two profiles regardless of their magnitude.
+    Cosine,
+    /// Pearson correlation of the weights.
+    Pearson,
+}
+
+impl Similarity {
+    /// Compare two equally long weight vectors, or `None` when the measure is
+    /// undefined for them (no shared categories, all zeros, no variance).
+    pub fn measure(self, a: &[f64], b: &[f64]) -> Option<f64> {
+        if a.is_empty() {
+            return None;
+        }
+        match self {
+            Similarity::MeanDifference => {
+                Some(a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f64>() / a.len() as f64)
+            }
+            Similarity::Euclidean => Some(
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| (x - y).powi(2))
+                    .sum::<f64>()
+                    .sqrt(),
+            ),
+            Similarity::Cosine => cosine_similarity(a, b),
+            Similarity::Pearson => pearson(a, b),
+        }
+    }
+
+    /// Whether larger values mean more alike, rather than further apart.
+    pub fn is_similarity(self) -> bool {
+        matches!(self, Similarity::Cosine | Similarity::Pearson)
+    }
+
+    /// A threshold that links only fairly close pairs on this measure's scale.
+    pub fn default_threshold(self) -> f64 {
+        match self {
+            Similarity::MeanDifference | Similarity::Euclidean => 5.0,
+            Similarity::Cosine => 0.99,
+            Similarity::Pearson => 0.9,
+        }
+    }
+
+    // Whether a pair with this value is close enough to be linked
+    fn links(self, value: f64, threshold: f64) -> bool {
+        if self.is_similarity() {
+            value >= threshold
+        } else {
+            value <= threshold
+        }
+    }
+}
+
+impl FromStr for Similarity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mean-difference" => Ok(Similarity::MeanDifference),
+            "euclidean" => Ok(Similarity::Euclidean),
+            "cosine" => Ok(Similarity::Cosine),
+            "pearson" => Ok(Similarity::Pearson),
+            _ => Err(format!(
+                "unknown similarity '{}', expected mean-difference, euclidean, cosine or pearson",
+                value
+            )),
+        }
+    }
+}
+
+/// How [`create_graph_with`] and [`create_ugraph_with`] decide which
+/// municipalities to link.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphConfig {
+    /// Cut-off on the `similarity` value, see [`Similarity`].
+    pub threshold: f64,
+    /// How two municipalities' weights are compared.
+    pub similarity: Similarity,
+    /// When set, a pair that passes the threshold is only linked if the Pearson
+    /// correlation `r` of its weights has `|r|` at least this large, and the edge
+    /// weight is `r` instead of the similarity value. Strongly anti-correlated
+    /// pairs are linked too, with a negative weight. Raising it never adds edges.
+    pub min_correlation: Option<f64>,
+}
+
+impl GraphConfig {
+    /// Link pairs by `similarity` at `threshold`, with no correlation filter.
+    pub fn new(threshold: f64, similarity: Similarity) -> Self {
+        GraphConfig {
+            threshold,
+            similarity,
+            min_correlation: None,
+        }
+    }
+
+    /// Link pairs by `similarity` at `threshold`, or at the measure's
+    /// [`Similarity::default_threshold`] when none is given.
+    pub fn with_default_threshold(threshold: Option<f64>, similarity: Similarity) -> Self {
+        GraphConfig::new(
+            threshold.unwrap_or_else(|| similarity.default_threshold()),
+            similarity,
+        )
+    }
+
+    /// The cost of crossing an edge of `weight` in a graph built with this
+    /// config, for path searches that need non-negative costs that grow as the
+    /// municipalities grow apart: a distance is its own cost, a cosine or Pearson
+    /// similarity `s` costs `1 - s` and a correlation weight `r` costs `1 - |r|`.
+    pub fn edge_cost(&self, weight: f64) -> f64 {
+        let cost = if self.min_correlation.is_some() {
+            1.0 - weight.abs()
+        } else if self.similarity.is_similarity() {
+            1.0 - weight
+        } else {
+            weight
+        };
+        // Rounding can leave a perfect similarity a hair above 1
+        cost.max(0.0)
+    }
+
+    /// How alike the municipalities joined by an edge of `weight` are, for
+    /// scores that need non-negative weights that grow as the municipalities
+    /// grow alike: a distance `d` counts as `1 / (1 + d)`, a cosine or Pearson
+    /// similarity as itself (negative ones as zero) and a correlation weight `r`
+    /// as `|r|`, so it falls as [`GraphConfig::edge_cost`] rises.
+    pub fn edge_similarity(&self, weight: f64) -> f64 {
+        if self.min_correlation.is_some() || self.similarity.is_similarity() {
+            (1.0 - self.edge_cost(weight)).max(0.0)
+        } else {
+            1.0 / (1.0 + weight.max(0.0))
+        }
+    }
+}
+
+/// Build a similarity graph with one node per municipality. Two municipalities are
+/// linked (in both directions) when the mean absolute difference of the weights of
+/// the categories they share is at most `threshold`; that difference is the edge weight.
+/// Lowering `threshold` never adds edges, so it controls how dense the graph is.
+pub fn create_graph<T: GraphData>(data: &[T], threshold: f64) -> DiGraph<&str, f64> {
+    similarity_graph(
+        data,
+        &GraphConfig::new(threshold, Similarity::MeanDifference),
+    )
+}
+
+/// Like [`create_graph`], but linking municipalities as `config` says.
+pub fn create_graph_with<'a, T: GraphData>(
+    data: &'a [T],
+    config: &GraphConfig,
+) -> DiGraph<&'a str, f64> {
+    similarity_graph(data, config)
+}
+
+/// Like [`create_graph`], but with a single undirected edge per linked pair.
+pub fn create_ugraph<T: GraphData>(data: &[T], threshold: f64) -> UnGraph<&str, f64> {
+    similarity_graph(
+        data,
+        &GraphConfig::new(threshold, Similarity::MeanDifference),
+    )
+}
+
+/// Like [`create_ugraph`], but linking municipalities as `config` says.
+pub fn create_ugraph_with<'a, T: GraphData>(
+    data: &'a [T],
+    config: &GraphConfig,
+) -> UnGraph<&'a str, f64> {
+    similarity_graph(data, config)
+}
+
+/// The minimum spanning forest of `graph`: every node, and for each connected
+/// component the edges of least total weight that still connect it. On a graph
+/// weighted by distance this is the backbone of closest pairs, a sparse
+/// alternative to the thresholded graph.
+pub fn minimum_spanning_tree<'a>(graph: &UnGraph<&'a str, f64>) -> UnGraph<&'a str, f64> {
+    UnGraph::from_elements(min_spanning_tree(graph))
+}
+
+/// The spanning forest over the closest pairs of a graph built with `config`:
+/// the [`minimum_spanning_tree`] for distances, and the maximum one for
+/// similarities and correlations, which are largest for the closest pairs.
+pub fn spanning_tree<'a>(
+    graph: &UnGraph<&'a str, f64>,
+    config: &GraphConfig,
+) -> UnGraph<&'a str, f64> {
+    if !config.similarity.is_similarity() && config.min_correlation.is_none() {
+        return minimum_spanning_tree(graph);
+    }
+    let negated = graph.map(|_, &name| name, |_, &weight| -weight);
+    minimum_spanning_tree(&negated).map(|_, &name| name, |_, &weight| -weight)
+}
+
+// Directed graphs get an edge each way, since similarity has no direction
+fn similarity_graph<'a, T: GraphData, Ty: EdgeType>(
+    data: &'a [T],
+    config: &GraphConfig,
+) -> Graph<&'a str, f64, Ty> {
+    let GraphConfig {
+        threshold,
+        similarity,
+        min_correlation,
+    } = *config;
+    let mut graph = Graph::default();
+    let mut nodes: HashMap<&str, NodeIndex> = HashMap::new();
+
+    for entry in data {
+        let name = entry.municipality();
+        nodes.entry(name).or_insert_with(|| graph.add_node(name));
+    }
+
+    for (i, entry1) in data.iter().enumerate() {
+        for entry2 in &data[i + 1..] {
+            if entry1.municipality() == entry2.municipality() {
+                continue;
+            }
+
+            let (weights1, weights2): (Vec<f64>, Vec<f64>) = entry1
+                .categories()
+                .into_iter()
+                .filter_map(|category| {
+                    Some((entry1.get_weight(category)?, entry2.get_weight(category)?))
+                })
+                .unzip();
+            let Some(mut value) = similarity.measure(&weights1, &weights2) else {
+                continue;
+            };
+            if !similarity.links(value, threshold) {
+                continue;
+            }
+            if let Some(min_correlation) = min_correlation {
+                match pearson(&weights1, &weights2) {
+                    Some(r) if r.abs() >= min_correlation => value = r,
+                    _ => continue,
+                }
+            }
+
+            let node1 = nodes[entry1.municipality()];
+            let node2 = nodes[entry2.municipality()];
+            graph.add_edge(node1, node2, value);
+            if graph.is_directed() {
+                graph.add_edge(node2, node1, value);
+            }
+        }
+    }
+
+    graph
+}
+
+/// The weakly connected components of `graph`: groups of nodes linked by edges
+/// in either direction. Each component lists its nodes in index order, and the
+/// components are ordered by their first node.
+pub fn connected_components<N, E>(graph: &DiGraph<N, E>) -> Vec<Vec<NodeIndex>> {
+    let mut union_find = UnionFind::new(graph.node_count());
+    for edge in graph.edge_references() {
+        union_find.union(edge.source().index(), edge.target().index());
+    }
+
+    let mut components: Vec<Vec<NodeIndex>> = Vec::new();
+    let mut component_of_root: HashMap<usize, usize> = HashMap::new();
+    for node in graph.node_indices() {
+        let root = union_find.find(node.index());
+        let component = *component_of_root.entry(root).or_insert_with(|| {
+            components.push(Vec::new());
+            components.len() - 1
+        });
+        components[component].push(node);
+    }
+
+    components
+}
+
+/// PageRank of every municipality in a graph built with `config`, computed by
+/// `iters` rounds of power iteration with the given `damping` factor. A node's
+/// rank is passed along its outgoing edges in proportion to
+/// [`GraphConfig::edge_similarity`] of their weights, so most of it flows to the
+/// most alike neighbors; nodes with no (or only zero-similarity) outgoing edges
+/// spread their rank over all nodes. The scores sum to 1.
+pub fn pagerank(
+    graph: &DiGraph<&str, f64>,
+    damping: f64,
+    iters: usize,
+    config: &GraphConfig,
+) -> HashMap<String, f64> {
+    let n = graph.node_count();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let out_weights: Vec<f64> = graph
+        .node_indices()
+        .map(|node| {
+            graph
+                .edges(node)
+                .map(|edge| config.edge_similarity(*edge.weight()))
+                .sum()
+        })
+        .collect();
+    let mut ranks = vec![1.0 / n as f64; n];
+
+    for _ in 0..iters {
+        let mut next = vec![(1.0 - damping) / n as f64; n];
+        let mut dangling = 0.0;
+        for node in graph.node_indices() {
+            let rank = ranks[node.index()];
+            let total = out_weights[node.index()];
+            if total <= 0.0 {
+                dangling += rank;
+                continue;
+            }
+            for edge in graph.edges(node) {
+                next[edge.target().index()] +=
+                    damping * rank * config.edge_similarity(*edge.weight()) / total;
+            }
+        }
+        for rank in &mut next {
+            *rank += damping * dangling / n as f64;
+        }
+        ranks = next;
+    }
+
+    graph
+        .node_indices()
+        .map(|node| (graph[node].to_string(), ranks[node.index()]))
+        .collect()
+}
+
+/// The cheapest path from municipality `from` to `to` in a graph built with
+/// `config`, as the names along it and its total cost, found with Dijkstra's
+/// algorithm. Each edge costs [`GraphConfig::edge_cost`] of its weight, so the
+/// path runs through the most alike pairs whether the graph is weighted by
+/// distance, similarity or correlation. Names are matched ignoring case. `None`
+/// when there is no such path, with a warning logged when either name isn't in
+/// the graph.
+pub fn shortest_path(
+    graph: &DiGraph<&str, f64>,
+    from: &str,
+    to: &str,
+    config: &GraphConfig,
+) -> Option<(Vec<String>, f64)> {
+    let find = |name: &str| {
+        let lowercase = name.to_lowercase();
+        let node = graph
+            .node_indices()
+            .find(|&node| graph[node].to_lowercase() == lowercase);
+        if node.is_none() {
+            warn!("'{}' is not a municipality of the graph", name);
+        }
+        node
+    };
+    let (start, goal) = (find(from)?, find(to)?);
+
+    // A* without a heuristic is Dijkstra's algorithm, and also returns the path
+    let (cost, path) = astar(
+        graph,
+        start,
+        |node| node == goal,
+        |edge| config.edge_cost(*edge.weight()),
+        |_| 0.0,
+    )?;
+    let names = path
+        .into_iter()
+        .map(|node| graph[node].to_string())
+        .collect();
+    Some((names, cost))
+}
+
+/// Strength (weighted degree) of every municipality in a graph built with
+/// `config`: the sum of [`GraphConfig::edge_similarity`] over its outgoing
+/// edges. The similarity graphs link each pair both ways, so this is how alike a
+/// municipality is to all of its neighbors together, whether the graph is
+/// weighted by distance, similarity or correlation; the municipalities with many
+/// close links, the most typical ones, score highest.
+pub fn node_strength(graph: &DiGraph<&str, f64>, config: &GraphConfig) -> HashMap<String, f64> {
+    graph
+        .node_indices()
+        .map(|node| {
+            (
+                graph[node].to_string(),
+                graph
+                    .edges(node)
+                    .map(|edge| config.edge_similarity(*edge.weight()))
+                    .sum(),
+            )
+        })
+        .collect()
+}
+
+/// Betweenness centrality of every municipality in a similarity graph: how many
+/// of the shortest paths between other municipalities pass through it, counting
+/// each edge as one step (Brandes' algorithm). Scores are divided by the
+/// `(n - 1)(n - 2)` ordered pairs of other nodes, so they lie in [0, 1];
+/// municipalities bridging otherwise separate groups score highest.
+pub fn betweenness_centrality(graph: &DiGraph<&str, f64>) -> HashMap<String, f64> {
+    let n = graph.node_count();
+    let mut centrality = vec![0.0; n];
+
+    for source in graph.node_indices() {
+        // Breadth-first search counting the shortest paths to every node
+        let mut order = Vec::with_capacity(n);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths = vec![0.0; n];
+        let mut depth = vec![usize::MAX; n];
+        paths[source.index()] = 1.0;
+        depth[source.index()] = 0;
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            let v = node.index();
+            order.push(v);
+            for neighbor in graph.neighbors_directed(node, Direction::Outgoing) {
+                let w = neighbor.index();
+                if depth[w] == usize::MAX {
+                    depth[w] = depth[v] + 1;
+                    queue.push_back(neighbor);
+                }
+                if depth[w] == depth[v] + 1 {
+                    paths[w] += paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+
+        // Pass each node's share of the paths back, furthest nodes first
+        let mut dependency = vec![0.0; n];
+        for &w in order.iter().rev() {
+            for &v in &predecessors[w] {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != source.index() {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+
+    let pairs = if n > 2 {
+        ((n - 1) * (n - 2)) as f64
+    } else {
+        1.0
+    };
+    graph
+        .node_indices()
+        .map(|node| (graph[node].to_string(), centrality[node.index()] / pairs))
+        .collect()
+}
+
+/// How many nodes have each degree, counting the edges in `direction`: in-degrees
+/// for [`Direction::Incoming`], out-degrees for [`Direction::Outgoing`]. The
+/// counts add up to the node count.
+pub fn degree_distribution(
+    graph: &DiGraph<&str, f64>,
+    direction: Direction,
+) -> HashMap<usize, usize> {
+    let mut distribution = HashMap::new();
+    for node in graph.node_indices() {
+        let degree = graph.edges_directed(node, direction).count();
+        *distribution.entry(degree).or_insert(0) += 1;
+    }
+    distribution
+}
+
+/// Link each common municipality's education profile to its population growth
+/// profile. Every municipality in both datasets gets an `"<name> (education)"`
+/// and a `"<name> (pop growth)"` node, joined by an edge weighted with the
+/// cosine similarity of the two profiles over their shared categories.
+/// Municipalities without a shared category get no edge.
+pub fn create_cross_graph(edu: &[EducationData], pop: &[PopGrowthData]) -> DiGraph<String, f64> {
+    let mut graph = DiGraph::new();
+
+    for (municipality, edu_entry, pop_entry) in filter_common_municipalities(edu, pop) {
+        let edu_node = graph.add_node(format!("{} (education)", municipality));
+        let pop_node = graph.add_node(format!("{} (pop growth)", municipality));
+
+        let (edu_weights, pop_weights): (Vec<f64>, Vec<f64>) = edu_entry
+            .categories()
+            .into_iter()
+            .filter_map(|category| {
+                Some((
+                    edu_entry.get_weight(category)?,
+                    pop_entry.get_weight(category)?,
+                ))
+            })
+            .unzip();
+        if let Some(similarity) = cosine_similarity(&edu_weights, &pop_weights) {
+            graph.add_edge(edu_node, pop_node, similarity);
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_input;
+    use crate::test_util::{fixture, records, temp_path};
+
+    // A graph of `names` with each of `edges` linking two of them both ways
+    fn graph_of<'a>(names: &[&'a str], edges: &[(usize, usize, f64)]) -> DiGraph<&'a str, f64> {
+        let mut graph = DiGraph::new();
+        let nodes: Vec<NodeIndex> = names.iter().map(|&name| graph.add_node(name)).collect();
+        for &(a, b, weight) in edges {
+            graph.add_edge(nodes[a], nodes[b], weight);
+            graph.add_edge(nodes[b], nodes[a], weight);
+        }
+        graph
+    }
+
+    #[test]
+    fn shortest_path_follows_a_path_graph() {
+        let graph = graph_of(
+            &["A", "B", "C", "D", "E"],
+            &[(0, 1, 1.0), (1, 2, 2.0), (2, 3, 0.5)],
+        );
+        let config = GraphConfig::new(5.0, Similarity::MeanDifference);
+
+        let (names, cost) = shortest_path(&graph, "a", "D", &config).unwrap();
+        assert_eq!(names, ["A", "B", "C", "D"]);
+        assert!((cost - 3.5).abs() < 1e-12);
+        assert_eq!(shortest_path(&graph, "A", "E", &config), None);
+        assert_eq!(shortest_path(&graph, "A", "Z", &config), None);
+    }
+
+    #[test]
+    fn shortest_path_prefers_the_most_similar_pairs() {
+        // A and C are barely alike, but both are close to B
+        let graph = graph_of(&["A", "B", "C"], &[(0, 1, 0.9), (1, 2, 0.9), (0, 2, 0.1)]);
+        let config = GraphConfig::new(0.0, Similarity::Cosine);
+
+        let (names, cost) = shortest_path(&graph, "A", "C", &config).unwrap();
+        assert_eq!(names, ["A", "B", "C"]);
+        assert!((cost - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shortest_path_counts_strong_anti_correlation_as_close() {
+        let graph = graph_of(
+            &["A", "B", "C"],
+            &[(0, 1, -0.95), (1, 2, -0.95), (0, 2, 0.2)],
+        );
+        let config = GraphConfig {
+            min_correlation: Some(0.1),
+            ..GraphConfig::new(5.0, Similarity::MeanDifference)
+        };
+
+        let (names, cost) = shortest_path(&graph, "A", "C", &config).unwrap();
+        assert_eq!(names, ["A", "B", "C"]);
+        assert!((cost - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn create_graph_links_close_municipalities_both_ways() {
+        let mut data = Vec::new();
+        for (name, literacy, enrollment) in [("A", 10.0, 10.0), ("B", 11.0, 9.0), ("C", 50.0, 50.0)]
+        {
+            let mut entry = EducationData::new(name);
+            entry.set(1, 2020, literacy);
+            entry.set(2, 2020, enrollment);
+            data.push(entry);
+        }
+
+        // Only A and B are within 5.0 of each other, one edge each way
+        let graph = create_graph(&data, 5.0);
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+        assert!(graph.edge_references().all(|edge| *edge.weight() == 1.0));
+        assert_eq!(create_graph(&data, 100.0).edge_count(), 6);
+    }
+
+    #[test]
+    fn graphml_has_an_edge_element_per_edge() {
+        let graph = graph_of(&["A", "B & C", "D"], &[(0, 1, 0.5), (1, 2, 1.5)]);
+        let path = temp_path("graph.graphml");
+        export_graphml(&graph, &path).unwrap();
+        let graphml = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(graphml.contains("<graphml"));
+        assert!(graphml.contains(r#"edgedefault="directed""#));
+        assert_eq!(graphml.matches("<node ").count(), 3);
+        assert_eq!(graphml.matches("<edge ").count(), graph.edge_count());
+        assert!(graphml.contains("B &amp; C"));
+    }
+
+    #[test]
+    fn undirected_graph_has_one_edge_per_linked_pair() {
+        let data = records(&[
+            ("A", &[(1, 1.0), (2, 2.0)]),
+            ("B", &[(1, 2.0), (2, 3.0)]),
+            ("C", &[(1, 3.0), (2, 4.0)]),
+            ("D", &[(1, 40.0), (2, 50.0)]),
+        ]);
+
+        let directed = create_graph(&data, 2.0);
+        let undirected = create_ugraph(&data, 2.0);
+        assert_eq!(undirected.node_count(), directed.node_count());
+        assert_eq!(undirected.edge_count(), 3);
+        assert_eq!(directed.edge_count(), 2 * undirected.edge_count());
+    }
+
+    #[test]
+    fn two_disconnected_triangles_are_two_components() {
+        let graph = graph_of(
+            &["A", "B", "C", "D", "E", "F"],
+            &[
+                (0, 1, 1.0),
+                (1, 2, 1.0),
+                (2, 0, 1.0),
+                (3, 4, 1.0),
+                (4, 5, 1.0),
+                (5, 3, 1.0),
+            ],
+        );
+        let components = connected_components(&graph);
+        let names: Vec<Vec<&str>> = components
+            .iter()
+            .map(|component| component.iter().map(|&node| graph[node]).collect())
+            .collect();
+        assert_eq!(names, [["A", "B", "C"], ["D", "E", "F"]]);
+    }
+
+    #[test]
+    fn pagerank_of_a_star_matches_the_hand_computed_ranks() {
+        let graph = graph_of(
+            &["Hub", "A", "B", "C"],
+            &[(0, 1, 1.0), (0, 2, 1.0), (0, 3, 1.0)],
+        );
+        let ranks = pagerank(
+            &graph,
+            0.85,
+            100,
+            &GraphConfig::new(5.0, Similarity::MeanDifference),
+        );
+
+        // The hub gets every leaf's rank, each leaf a third of the hub's:
+        // hub = 0.15 / 4 + 0.85 * (1 - hub), so hub = 0.8875 / 1.85
+        let hub = 0.8875 / 1.85;
+        assert!((ranks["Hub"] - hub).abs() < 1e-6);
+        for leaf in ["A", "B", "C"] {
+            assert!((ranks[leaf] - (1.0 - hub) / 3.0).abs() < 1e-6);
+        }
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degree_distribution_sums_to_the_node_count() {
+        let graph = graph_of(
+            &["Hub", "A", "B", "C", "Alone"],
+            &[(0, 1, 1.0), (0, 2, 1.0), (0, 3, 1.0), (1, 2, 1.0)],
+        );
+        for direction in [Direction::Outgoing, Direction::Incoming] {
+            let distribution = degree_distribution(&graph, direction);
+            assert_eq!(distribution.values().sum::<usize>(), graph.node_count());
+            assert_eq!(
+                distribution,
+                HashMap::from([(3, 1), (2, 2), (1, 1), (0, 1)])
+            );
+        }
+    }
+
+    #[test]
+    fn edge_count_falls_as_the_threshold_shrinks() {
+        let data = records(&[
+            ("A", &[(1, 0.0), (2, 0.0)]),
+            ("B", &[(1, 1.0), (2, 1.0)]),
+            ("C", &[(1, 3.0), (2, 3.0)]),
+            ("D", &[(1, 7.0), (2, 7.0)]),
+            ("E", &[(1, 15.0), (2, 15.0)]),
+        ]);
+        let counts: Vec<usize> = [20.0, 10.0, 5.0, 2.0, 1.0, 0.5]
+            .iter()
+            .map(|&threshold| create_graph(&data, threshold).edge_count())
+            .collect();
+        assert!(
+            counts.windows(2).all(|pair| pair[1] <= pair[0]),
+            "{:?}",
+            counts
+        );
+        assert_eq!(counts.first(), Some(&20));
+        assert_eq!(counts.last(), Some(&0));
+    }
+
+    #[test]
+    fn dot_output_labels_edges_only_when_asked() {
+        let graph = graph_of(&["A", "B"], &[(0, 1, 2.5)]);
+        let path = temp_path("weights.dot");
+
+        visualize_graph(&graph, &path, true).unwrap();
+        let labeled = std::fs::read_to_string(&path).unwrap();
+        visualize_graph(&graph, &path, false).unwrap();
+        let unlabeled = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(labeled.contains("label = \"2.5\""));
+        assert!(!unlabeled.contains("2.5"));
+    }
+
+    #[test]
+    fn cosine_of_vectors_pointing_the_same_way_is_one() {
+        let cosine = Similarity::Cosine
+            .measure(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0])
+            .unwrap();
+        assert!((cosine - 1.0).abs() < 1e-12);
+
+        let orthogonal = Similarity::Cosine
+            .measure(&[1.0, 0.0], &[0.0, 5.0])
+            .unwrap();
+        assert!(orthogonal.abs() < 1e-12);
+        assert_eq!(Similarity::Cosine.measure(&[0.0, 0.0], &[1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn minimum_spanning_tree_of_a_square_drops_its_heaviest_edges() {
+        // A square with a diagonal, the edges weighted from 1 to 5
+        let mut graph = UnGraph::new_undirected();
+        let nodes: Vec<NodeIndex> = ["A", "B", "C", "D"]
+            .iter()
+            .map(|&name| graph.add_node(name))
+            .collect();
+        for (a, b, weight) in [
+            (0, 1, 1.0),
+            (1, 2, 2.0),
+            (2, 3, 3.0),
+            (3, 0, 4.0),
+            (0, 2, 5.0),
+        ] {
+            graph.add_edge(nodes[a], nodes[b], weight);
+        }
+
+        let tree = minimum_spanning_tree(&graph);
+        assert_eq!(tree.node_count(), 4);
+        assert_eq!(tree.edge_count(), 3);
+        let mut weights: Vec<f64> = tree.edge_references().map(|edge| *edge.weight()).collect();
+        weights.sort_unstable_by(f64::total_cmp);
+        assert_eq!(weights, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn the_middle_of_a_path_has_the_highest_betweenness() {
+        let graph = graph_of(
+            &["A", "B", "C", "D", "E"],
+            &[(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (3, 4, 1.0)],
+        );
+        let centrality = betweenness_centrality(&graph);
+
+        // C lies on the paths between A or B and D or E, both ways: 8 of 12 pairs
+        assert!((centrality["C"] - 8.0 / 12.0).abs() < 1e-12);
+        assert!((centrality["B"] - 6.0 / 12.0).abs() < 1e-12);
+        assert_eq!(centrality["A"], 0.0);
+        assert!(centrality
+            .iter()
+            .all(|(name, &score)| name == "C" || score < centrality["C"]));
+    }
+
+    #[test]
+    fn raising_min_correlation_never_adds_edges() {
+        let data = records(&[
+            ("A", &[(1, 1.0), (2, 2.0), (3, 3.0)]),
+            ("B", &[(1, 2.0), (2, 4.1), (3, 6.0)]),
+            ("C", &[(1, 3.0), (2, 2.0), (3, 1.0)]),
+            ("D", &[(1, 1.0), (2, 3.0), (3, 2.0)]),
+            ("E", &[(1, 5.0), (2, 5.5), (3, 9.0)]),
+        ]);
+        let edge_count = |min_correlation| {
+            let config = GraphConfig {
+                min_correlation: Some(min_correlation),
+                ..GraphConfig::new(100.0, Similarity::MeanDifference)
+            };
+            let graph = create_graph_with(&data, &config);
+            assert!(graph
+                .edge_references()
+                .all(|edge| edge.weight().abs() >= min_correlation));
+            graph.edge_count()
+        };
+
+        let counts: Vec<usize> = (0..=10)
+            .map(|step| edge_count(step as f64 / 10.0))
+            .collect();
+        assert!(
+            counts.windows(2).all(|pair| pair[1] <= pair[0]),
+            "{:?}",
+            counts
+        );
+        // Every pair at zero, and at most the perfectly anti-correlated A and C (r = -1) at one
+        assert_eq!(counts[0], 20);
+        assert!(counts[10] <= 2);
+    }
+
+    #[test]
+    fn strength_sums_the_similarities_of_each_node() {
+        let graph = graph_of(
+            &["A", "B", "C", "D"],
+            &[(0, 1, 0.5), (0, 2, 0.25), (1, 2, 0.75)],
+        );
+
+        // As cosine similarities the weights are summed as they are
+        let strength = node_strength(&graph, &GraphConfig::new(0.0, Similarity::Cosine));
+        assert_eq!(strength.len(), 4);
+        assert_eq!(strength["A"], 0.75);
+        assert_eq!(strength["B"], 1.25);
+        assert_eq!(strength["C"], 1.0);
+        assert_eq!(strength["D"], 0.0);
+
+        // As distances each weight d counts 1 / (1 + d), which flips the order
+        let strength = node_strength(&graph, &GraphConfig::new(5.0, Similarity::MeanDifference));
+        assert!((strength["A"] - (1.0 / 1.5 + 1.0 / 1.25)).abs() < 1e-12);
+        assert!((strength["B"] - (1.0 / 1.5 + 1.0 / 1.75)).abs() < 1e-12);
+        assert!((strength["C"] - (1.0 / 1.25 + 1.0 / 1.75)).abs() < 1e-12);
+        assert!(strength["A"] > strength["C"] && strength["C"] > strength["B"]);
+        assert_eq!(strength["D"], 0.0);
+    }
+
+    #[test]
+    fn pagerank_sends_more_rank_to_the_more_alike_neighbor() {
+        // As distances, the hub is 0.2 from A and 0.9 from B
+        let graph = graph_of(&["Hub", "A", "B"], &[(0, 1, 0.2), (0, 2, 0.9)]);
+        let ranks = pagerank(
+            &graph,
+            0.85,
+            100,
+            &GraphConfig::new(5.0, Similarity::MeanDifference),
+        );
+        assert!(ranks["A"] > ranks["B"], "{:?}", ranks);
+
+        // As cosine similarities, the same weights make B the more alike one
+        let ranks = pagerank(
+            &graph,
+            0.85,
+            100,
+            &GraphConfig::new(0.0, Similarity::Cosine),
+        );
+        assert!(ranks["B"] > ranks["A"], "{:?}", ranks);
+    }
+
+    #[test]
+    fn edge_list_round_trip_keeps_nodes_and_edges() {
+        let education = read_input::<EducationData>(&fixture("education.json")).unwrap();
+        let graph = create_graph(&education, 5.0);
+        assert!(graph.edge_count() > 0);
+        assert!(graph
+            .node_indices()
+            .any(|node| graph.neighbors_undirected(node).next().is_none()));
+
+        let path = &temp_path("edgelist.tsv");
+        save_graph_edgelist(&graph, path).unwrap();
+        let loaded = load_graph_edgelist(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.node_count(), graph.node_count());
+        assert_eq!(loaded.edge_count(), graph.edge_count());
+        let mut names: Vec<&str> = loaded
+            .node_indices()
+            .map(|node| loaded[node].as_str())
+            .collect();
+        names.sort();
+        let mut expected: Vec<&str> = graph.node_indices().map(|node| graph[node]).collect();
+        expected.sort();
+        assert_eq!(names, expected);
+        for edge in graph.edge_indices() {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            let (source, target) = (graph[source], graph[target]);
+            assert!(loaded.edge_indices().any(|loaded_edge| {
+                let (s, t) = loaded.edge_endpoints(loaded_edge).unwrap();
+                loaded[s] == source && loaded[t] == target && loaded[loaded_edge] == graph[edge]
+            }));
+        }
+    }
+
+    #[test]
+    fn dot_output_matches_graph_direction() {
+        let education = read_input::<EducationData>(&fixture("education.json")).unwrap();
+        let (directed, undirected) = (&temp_path("directed.dot"), &temp_path("undirected.dot"));
+
+        visualize_graph(&create_graph(&education, 5.0), directed, true).unwrap();
+        visualize_graph(&create_ugraph(&education, 5.0), undirected, false).unwrap();
+        let directed_dot = std::fs::read_to_string(directed).unwrap();
+        let undirected_dot = std::fs::read_to_string(undirected).unwrap();
+        std::fs::remove_file(directed).unwrap();
+        std::fs::remove_file(undirected).unwrap();
+
+        assert!(directed_dot.starts_with("digraph {"));
+        assert!(directed_dot.contains(" -> "));
+        assert!(directed_dot.contains("label = \"1"));
+        assert!(undirected_dot.starts_with("graph {"));
+        assert!(undirected_dot.contains(" -- "));
+        assert!(!undirected_dot.contains("label = \"1"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Graph building and clustering for municipal education and population growth data.
+
+mod analysis;
+mod cluster;
+mod clustering;
+mod data;
+mod error;
+mod export;
+mod graph;
+mod output;
+mod pipeline;
+mod plot;
+mod report;
+
+pub use analysis::{
+    completeness, correlation_by_municipality, cosine_similarity, distance_matrix, histogram,
+    nearest_neighbor, pearson, rank_trends, summarize, top_n, trend, Summary,
+};
+pub use cluster::{run_clustering, Algorithm, ClusterConfig, KMethod};
+pub use clustering::{
+    assign_to_nearest_centroid, best_k, best_k_with_progress, canonicalize_assignments,
+    canonicalize_clusters, cluster_by_name, cluster_outliers, constant_categories, dbscan, dist,
+    extract_features, extract_features_with, fuzzy_cmeans, gap_statistic,
+    gap_statistic_with_progress, geo_clusters, haversine, hierarchical_cluster, imputed_values,
+    inertia, joint_features, k_means_clustering, k_means_clustering_with_progress,
+    k_means_features, k_means_features_with_progress, k_medoids, most_ambiguous, pca_project,
+    silhouette_score, standardize, standardize_point, weight_features, ClusteringResult,
+    Dendrogram, Distance, Imputation, KMeansConfig, Linkage, Merge,
+};
+pub use data::{
+    all_categories, bucketize, category_counts, deduplicate, exclude, filter_by_year,
+    filter_common_municipalities, filter_common_municipalities_with, find_by_municipality,
+    handle_non_finite, municipality_index, normalize_name, only_in_first, only_in_first_with,
+    only_in_second, only_in_second_with, parse_csv, parse_csv_with, parse_json, parse_ndjson,
+    parse_ndjson_iter, read_bucket_mapping, read_category_names, read_coordinates, read_data,
+    read_data_csv, read_data_csv_with, read_data_iter, read_data_ndjson, read_input,
+    read_input_with, read_inputs, restrict_to_common, validate, Aggregate, Delimiter,
+    DuplicatePolicy, EducationData, FromParts, GraphData, NameNormalization, NonFinitePolicy,
+    PopGrowthData,
+};
+pub use error::AppError;
+pub use export::{
+    fmt_f64, read_report_json, round_f64, write_clusters_csv, write_clusters_html,
+    write_distance_matrix_csv, write_report_json, DatasetReport, Report,
+};
+pub use graph::{
+    betweenness_centrality, connected_components, create_cross_graph, create_graph,
+    create_graph_with, create_ugraph, create_ugraph_with, degree_distribution, export_graphml,
+    load_graph_edgelist, minimum_spanning_tree, node_strength, pagerank, render_dot_to_png,
+    save_graph_edgelist, shortest_path, spanning_tree, visualize_graph, GraphConfig, Similarity,
+};
+pub use output::{Output, OutputMode};
+pub use pipeline::{
+    print_paths, run_graphs, run_stats, Completeness, Dataset, Datasets, FeatureConfig, Features,
+    GraphsConfig, InputConfig, JointFeatures, StatsConfig,
+};
+pub use plot::{
+    axis_range, cluster_color, draw_clusters, feature_coordinates, plot_categories,
+    plot_cluster_sizes, plot_clusters, plot_clusters_side_by_side, plot_convergence,
+    plot_degree_distribution, plot_histogram, plot_map, ClusterPanel,
+};
+pub use report::{
+    print_ambiguous, print_category_counts, print_cluster_report, print_completeness,
+    print_components, print_iterations, print_medoids, print_path, print_record, print_summary,
+    print_top_n, print_top_ranks, print_trends,
+};
+
+// Helpers shared by the unit tests of every module
+#[cfg(test)]
+pub(crate) mod test_util {
+    use crate::EducationData;
+
+    // A file name of its own in the temporary directory
+    pub fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    // Write `contents` to a file of its own in the temporary directory
+    pub fn temp_file(name: &str, contents: &str) -> String {
+        let path = temp_path(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // A file of the integration test fixtures
+    pub fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    // Education records with the given category weights, all from 2020
+    pub fn records(rows: &[(&str, &[(i32, f64)])]) -> Vec<EducationData> {
+        rows.iter()
+            .map(|&(name, weights)| {
+                let mut entry = EducationData::new(name);
+                for &(category, weight) in weights {
+                    entry.set(category, 2020, weight);
+                }
+                entry
+            })
+            .collect()
+    }
+}
@@ -1,213 +1,788 @@
-use petgraph::dot::{Config, Dot};
-use petgraph::graph::DiGraph;
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::io::Write;
-use k_means::KMeans;
-use plotters::prelude::*;
-use plotters::style::PointStyle;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct EducationData {
-    municipality: String,
-    data: HashMap<i32, (i32, f64)>,
+use clap::{Args, Parser, Subcommand};
+use finalproject::{
+    print_category_counts, print_completeness, print_paths, read_coordinates, run_clustering,
+    run_graphs, run_stats, Aggregate, Algorithm, ClusterConfig, Datasets, Delimiter, Distance,
+    DuplicatePolicy, FeatureConfig, GraphConfig, GraphsConfig, Imputation, InputConfig,
+    KMeansConfig, KMethod, Linkage, NameNormalization, NonFinitePolicy, Output, OutputMode,
+    Similarity, StatsConfig,
+};
+use log::{info, LevelFilter};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Cluster and graph municipal education and population growth data.
+///
+/// Without a subcommand, runs every step: the statistics, the similarity graphs
+/// and the clustering, taking all of their flags.
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    print: PrintArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    stats: StatsArgs,
+
+    #[command(flatten)]
+    similarity: SimilarityArgs,
+
+    #[command(flatten)]
+    graph: GraphArgs,
+
+    #[command(flatten)]
+    cluster: ClusterArgs,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct PopGrowthData {
-    municipality: String,
-    data: HashMap<i32, (i32, f64)>,
-}
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Cluster the municipalities of each dataset, then plot and export the clusters
+    Cluster {
+        #[command(flatten)]
+        input: InputArgs,
 
-trait GraphData {
-    fn municipality(&self) -> &str;
-    fn get_weight(&self, category: i32) -> Option<f64>;
-}
+        #[command(flatten)]
+        print: PrintArgs,
 
-impl GraphData for EducationData {
-    fn municipality(&self) -> &str {
-        &self.municipality
-    }
+        #[command(flatten)]
+        output: OutputArgs,
 
-    fn get_weight(&self, category: i32) -> Option<f64> {
-        self.data.get(&category).map(|&(_, weight)| weight)
-    }
-}
+        #[command(flatten)]
+        cluster: ClusterArgs,
+    },
 
-impl GraphData for PopGrowthData {
-    fn municipality(&self) -> &str {
-        &self.municipality
-    }
+    /// Build, analyze and write the similarity graphs of each dataset
+    Graph {
+        #[command(flatten)]
+        input: InputArgs,
 
-    fn get_weight(&self, category: i32) -> Option<f64> {
-        self.data.get(&category).map(|&(_, weight)| weight)
-    }
+        #[command(flatten)]
+        print: PrintArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        similarity: SimilarityArgs,
+
+        #[command(flatten)]
+        graph: GraphArgs,
+    },
+
+    /// Print summary statistics, rankings and correlations of the datasets
+    Stats {
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        print: PrintArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        stats: StatsArgs,
+    },
+
+    /// Print the cheapest path between two municipalities in each similarity graph
+    Path {
+        /// Municipality the path starts from
+        from: String,
+
+        /// Municipality the path ends at
+        to: String,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        print: PrintArgs,
+
+        #[command(flatten)]
+        similarity: SimilarityArgs,
+    },
+}
+
+/// Reading and filtering the data files
+#[derive(Debug, Args)]
+struct InputArgs {
+    /// Education data file: a JSON array, NDJSON (.ndjson, .jsonl), CSV (.csv) or
+    /// TSV (.tsv), optionally gzip-compressed (.gz); `-` reads a JSON array from
+    /// stdin. Repeat the flag or separate paths by commas to combine several files
+    #[arg(long, value_delimiter = ',', default_value = "educationstats.txt")]
+    education: Vec<String>,
+
+    /// Population growth data file (same formats as --education, but only one
+    /// path of the two can be `-`); may also be given several times
+    #[arg(long, value_delimiter = ',', default_value = "popgrowthstats.txt")]
+    popgrowth: Vec<String>,
+
+    /// Read both data files as delimited text split on this, comma or tab, whatever
+    /// their extension
+    #[arg(long)]
+    delimiter: Option<Delimiter>,
+
+    /// What to do with repeated municipalities in one dataset, whether within a file
+    /// or across the files combined into it: first, last, merge (later records win
+    /// on shared categories), mean (shared categories are averaged) or error
+    #[arg(long, default_value = "error")]
+    duplicates: DuplicatePolicy,
+
+    /// What to do with NaN and infinite values: error or zero
+    #[arg(long, default_value = "error")]
+    non_finite: NonFinitePolicy,
+
+    /// Category key file naming the education categories, one `number; name` per line,
+    /// or a .json object from number to name
+    #[arg(long, default_value = "edukey.txt")]
+    education_key: String,
+
+    /// Category key file naming the population growth categories
+    #[arg(long, default_value = "popkey.txt")]
+    popgrowth_key: String,
+
+    /// Collapse the education categories into buckets, read from a file with one
+    /// `category; bucket` line per category; name the buckets with --education-key
+    #[arg(long)]
+    education_buckets: Option<String>,
+
+    /// How bucketed categories are combined: sum or mean
+    #[arg(long, default_value = "sum")]
+    bucket_aggregate: Aggregate,
+
+    /// Reject input values below this
+    #[arg(long)]
+    min_value: Option<f64>,
+
+    /// Reject input values above this
+    #[arg(long)]
+    max_value: Option<f64>,
+
+    /// Print the categories found in each data file and how many municipalities
+    /// have each, then exit
+    #[arg(long)]
+    list_categories: bool,
+
+    /// Only analyze values measured in this year
+    #[arg(long)]
+    year: Option<i32>,
+
+    /// Join the datasets ignoring case, surrounding whitespace and diacritics in names
+    #[arg(long)]
+    normalize_names: bool,
+
+    /// Leave out the municipality with this name (ignoring case); repeatable
+    #[arg(long, value_name = "NAME")]
+    exclude_municipality: Vec<String>,
+
+    /// Leave out the category with this number in both datasets; repeatable
+    #[arg(long, value_name = "ID")]
+    exclude_category: Vec<i32>,
+
+    /// Drop the municipalities that have less than this fraction (0 to 1) of their
+    /// dataset's categories
+    #[arg(long, value_parser = parse_fraction)]
+    min_completeness: Option<f64>,
+
+    /// Analyze only the municipalities present in both datasets, so the education
+    /// and pop growth results cover the same municipalities
+    #[arg(long)]
+    common_only: bool,
 }
 
-fn visualize_graph<T>(
-    graph: &DiGraph<&str, T>,
-    file_path: &str,
-) -> Result<(), Box<dyn std::error::Error>>
-where
-    T: Serialize + std::fmt::Debug,
-{
-    let mut file = File::create(file_path)?;
-    let dot = Dot::with_config(graph, &[Config::EdgeNoLabel]);
-    write!(file, "{:?}", dot)?;
+/// What is printed to the terminal
+#[derive(Debug, Args)]
+struct PrintArgs {
+    /// Decimal places of every number printed or written
+    #[arg(long, default_value_t = 4)]
+    precision: usize,
 
-    Ok(())
+    /// Log progress to stderr, down to debug messages (RUST_LOG also works)
+    #[arg(short, long)]
+    verbose: bool,
 }
 
-fn main() {
-    // Read education data
-    let education_data =
-        read_data::<EducationData>("educationstats.txt").expect("Error reading education data");
+/// Where and how the graphs, plots and other files are written
+#[derive(Debug, Args)]
+struct OutputArgs {
+    /// Write the plots as SVG instead of PNG; the Graphviz renderings of the
+    /// graphs stay PNG
+    #[arg(long)]
+    svg: bool,
+
+    /// Run the analysis and list the files it would write, without writing any
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Only print the statistics and clusters: write no graphs, plots, CSV or
+    /// other files, and don't list them either
+    #[arg(long, conflicts_with = "dry_run")]
+    stats_only: bool,
+
+    /// Directory the graphs and plots are written to
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+}
 
-    // Read pop growth data
-    let pop_growth_data =
-        read_data::<PopGrowthData>("popgrowthstats.txt").expect("Error reading pop growth data");
+/// The per-municipality statistics
+#[derive(Debug, Args)]
+struct StatsArgs {
+    /// Print every category of the municipality with this name (ignoring case)
+    #[arg(long, value_name = "NAME")]
+    inspect: Option<String>,
+
+    /// Print the N municipalities with the largest weight of CATEGORY in each dataset
+    #[arg(long, num_args = 2, value_names = ["CATEGORY", "N"])]
+    top_n: Vec<usize>,
+
+    /// Plot the distribution of CATEGORY's weights in each dataset that has it;
+    /// may be given several times
+    #[arg(long, value_name = "CATEGORY")]
+    histogram: Vec<i32>,
+
+    /// Number of bins of the --histogram plots
+    #[arg(long, default_value_t = 10)]
+    bins: usize,
+}
 
-    // Filter common municipalities
-    let common_municipalities = filter_common_municipalities(&education_data, &pop_growth_data);
+/// Which municipalities the similarity graphs link
+#[derive(Debug, Args)]
+struct SimilarityArgs {
+    /// How the similarity graphs compare municipalities: mean-difference, euclidean,
+    /// cosine or pearson
+    #[arg(long = "similarity", default_value = "mean-difference")]
+    measure: Similarity,
+
+    /// Link cutoff for the similarity graphs: the largest distance, or the smallest
+    /// cosine or correlation, at which two municipalities are linked
+    /// [default: 5 for distances, 0.99 for cosine, 0.9 for pearson]
+    #[arg(long)]
+    threshold: Option<f64>,
+
+    /// Only link pairs whose weights have a Pearson correlation of at least this
+    /// size (either sign), on top of the threshold, and weight edges by the correlation
+    #[arg(long)]
+    min_correlation: Option<f64>,
+}
 
-    // Create graphs from your data
-    let education_graph: DiGraph<&str, f64> = create_graph(&education_data);
-    let pop_growth_graph: DiGraph<&str, f64> = create_graph(&pop_growth_data);
+/// The analyses and files of the similarity graphs
+#[derive(Debug, Args)]
+struct GraphArgs {
+    /// Print the cheapest path between two municipalities in each similarity graph
+    #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+    path: Vec<String>,
+
+    /// Label the edges of the DOT graphs with their weights
+    #[arg(long)]
+    show_weights: bool,
+
+    /// Also write the minimum spanning tree of each similarity graph, linking every
+    /// municipality through its closest pairs
+    #[arg(long)]
+    mst: bool,
+
+    /// Write the similarity graphs with one undirected edge per linked pair
+    #[arg(long)]
+    undirected: bool,
+}
 
-    // Visualize the graphs
-    if let Err(err) = visualize_graph(&education_graph, "education_graph.dot") {
-        eprintln!("Error: {}", err);
-    }
+/// The clustering and its reports, exports and plots
+#[derive(Debug, Args)]
+struct ClusterArgs {
+    /// Clustering algorithm: k-means, k-medoids (PAM, centering each cluster on a
+    /// representative municipality), dbscan (leaving outliers unclustered as noise)
+    /// or hierarchical (agglomerative, cut at --height)
+    #[arg(long, default_value = "k-means")]
+    algorithm: Algorithm,
+
+    /// Seed of the random centroid initialization and of every other random
+    /// choice of the clustering, so runs can be repeated
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// Number of k-means restarts; the one with the lowest inertia is kept
+    #[arg(long, default_value_t = 10)]
+    n_init: usize,
+
+    /// Most Lloyd iterations per k-means restart
+    #[arg(long, default_value_t = 100)]
+    max_iterations: usize,
+
+    /// Cluster on the raw category values instead of standardizing each category to
+    /// zero mean and unit variance first, so large-scale categories dominate
+    #[arg(long)]
+    no_standardize: bool,
+
+    /// Comma-separated feature weights, one per category in ascending category order;
+    /// categories without a weight count once
+    #[arg(long, value_delimiter = ',')]
+    weights: Vec<f64>,
+
+    /// Leave out of the features the categories with the same value for every
+    /// municipality, after warning about them; --weights then covers the rest
+    #[arg(long)]
+    drop_constant: bool,
+
+    /// How a municipality's missing categories are filled in for clustering: zero,
+    /// or the category's mean or median over the municipalities that have it
+    #[arg(long, default_value = "zero")]
+    impute: Imputation,
+
+    /// Number of k-means clusters; chosen with --k-method when omitted
+    #[arg(long, value_parser = parse_clusters)]
+    clusters: Option<usize>,
+
+    /// With k-means, also run fuzzy c-means with the same k and print the N
+    /// municipalities split most evenly between clusters
+    #[arg(long, value_name = "N")]
+    fuzzy: Option<usize>,
+
+    /// Fuzzifier of --fuzzy, above 1; larger values give softer memberships
+    #[arg(long, default_value_t = 2.0)]
+    fuzziness: f64,
+
+    /// How the number of clusters is chosen when --clusters is omitted: elbow, the
+    /// elbow of the inertia curve, or gap, Tibshirani's gap statistic against
+    /// uniform reference data
+    #[arg(long, default_value = "elbow")]
+    k_method: KMethod,
+
+    /// With k-means, also plot the inertia after each iteration of every dataset's
+    /// kept restart, to see whether --max-iterations was enough
+    #[arg(long)]
+    convergence: bool,
+
+    /// Uniform reference datasets drawn per k by --k-method gap
+    #[arg(long, default_value_t = 10)]
+    gap_refs: usize,
+
+    /// CSV file with a municipality,latitude,longitude header; the clusters are
+    /// also plotted on maps of these coordinates when given
+    #[arg(long, value_name = "FILE")]
+    coordinates: Option<String>,
+
+    /// Also cluster the municipalities in --coordinates into this many groups by
+    /// great-circle distance
+    #[arg(long, value_name = "K", value_parser = parse_clusters)]
+    geo_clusters: Option<usize>,
+
+    /// Standard deviations above its cluster's mean distance to the centroid at which
+    /// a k-means member is reported as an outlier
+    #[arg(long, default_value_t = 2.0)]
+    outlier_z: f64,
+
+    /// DBSCAN neighborhood radius, in standardized feature units
+    #[arg(long, default_value_t = 1.0)]
+    eps: f64,
+
+    /// Neighbors (the point included) a DBSCAN core point needs within --eps
+    #[arg(long, default_value_t = 4)]
+    min_points: usize,
+
+    /// Distance k-means measures between points and centroids: euclidean,
+    /// manhattan or chebyshev
+    #[arg(long, default_value = "euclidean")]
+    metric: Distance,
+
+    /// Linkage for hierarchical clustering: single, complete or average
+    #[arg(long, default_value = "average")]
+    linkage: Linkage,
+
+    /// Height at which the hierarchical clustering dendrogram is cut into clusters
+    #[arg(long, default_value_t = 2.0)]
+    height: f64,
+
+    /// Read education records from this file (same formats as --education) and print
+    /// the education cluster each would join; needs k-means or k-medoids
+    #[arg(long, value_name = "FILE")]
+    classify: Option<String>,
+
+    /// Also write each dataset's pairwise municipality distance matrix to CSV
+    #[arg(long)]
+    distance_matrix: bool,
+
+    /// Also write the k-means clusters, centroids and scores to report.json
+    #[arg(long)]
+    report: bool,
+
+    /// Use logarithmic axes for the category scatter plots
+    #[arg(long)]
+    log_scale: bool,
+
+    /// Also write interactive HTML cluster plots that name each point on hover
+    #[arg(long)]
+    html: bool,
+
+    /// Show progress bars for the sweeps choosing k and the k-means restarts when stderr is a terminal
+    #[arg(long)]
+    progress: bool,
+}
 
-    if let Err(err) = visualize_graph(&pop_growth_graph, "pop_growth_graph.dot") {
-        eprintln!("Error: {}", err);
+impl Cli {
+    // The printing flags, from the subcommand if there is one
+    fn print_args(&self) -> &PrintArgs {
+        match &self.command {
+            None => &self.print,
+            Some(
+                Command::Cluster { print, .. }
+                | Command::Graph { print, .. }
+                | Command::Stats { print, .. }
+                | Command::Path { print, .. },
+            ) => print,
+        }
     }
+}
 
-    // Perform k-means clustering
-    let k = 3; // Specify the number of clusters
-    let education_clusters = k_means_clustering(&education_data, k);
-    let pop_growth_clusters = k_means_clustering(&pop_growth_data, k);
-
-    // Output clusters
-    println!("Education Clusters: {:?}", education_clusters);
-    println!("Pop Growth Clusters: {:?}", pop_growth_clusters);
+fn parse_clusters(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("k must be at least 1".to_string()),
+        Ok(k) => Ok(k),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-    // Plot clusters (example using plotters crate)
-    plot_clusters(&education_clusters, "education_clusters.png");
-    plot_clusters(&pop_growth_clusters, "pop_growth_clusters.png");
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        Ok(_) => Err("expected a fraction from 0 to 1".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-fn read_data<T: for<'de> Deserialize<'de>>(
-    file_path: &str,
-) -> Result<Vec<T>, Box<dyn std::error::Error>> {
-    let file = File::open(file_path)?;
-    let reader = io::BufReader::new(file);
+fn main() {
+    let cli = Cli::parse();
+
+    // Log progress from RUST_LOG, or everything down to debug messages with --verbose
+    let mut logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"));
+    if cli.print_args().verbose {
+        logger.filter_level(LevelFilter::Debug);
+    }
+    logger.init();
 
-    let records: Result<Vec<T>, _> = serde_json::from_reader(reader);
-    records
+    if let Err(err) = run(cli) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
 
-fn create_graph<T: GraphData>(data: &[T]) -> DiGraph<&str, f64> {
-    // Your existing implementation
+// Run the subcommand given, or without one every step in turn on the same datasets
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let out = &mut io::stdout();
+    match cli.command {
+        None => {
+            let Some(datasets) = load(out, &cli.input)? else {
+                return Ok(());
+            };
+            let output = open_output(&cli.output)?;
+            let coordinates = cli
+                .cluster
+                .coordinates
+                .as_deref()
+                .map(read_coordinates)
+                .transpose()?;
+            stats(out, &datasets, &cli.stats, &cli.print, &output)?;
+            graph(
+                out,
+                &datasets,
+                &cli.similarity,
+                &cli.graph,
+                &cli.print,
+                &output,
+            )?;
+            cluster(
+                out,
+                &datasets,
+                &cli.cluster,
+                coordinates,
+                &cli.input,
+                &cli.print,
+                &output,
+            )
+        }
+        Some(Command::Cluster {
+            input,
+            print,
+            output,
+            cluster: args,
+        }) => {
+            let Some(datasets) = load(out, &input)? else {
+                return Ok(());
+            };
+            let coordinates = args
+                .coordinates
+                .as_deref()
+                .map(read_coordinates)
+                .transpose()?;
+            cluster(
+                out,
+                &datasets,
+                &args,
+                coordinates,
+                &input,
+                &print,
+                &open_output(&output)?,
+            )
+        }
+        Some(Command::Graph {
+            input,
+            print,
+            output,
+            similarity,
+            graph: args,
+        }) => {
+            let Some(datasets) = load(out, &input)? else {
+                return Ok(());
+            };
+            graph(
+                out,
+                &datasets,
+                &similarity,
+                &args,
+                &print,
+                &open_output(&output)?,
+            )
+        }
+        Some(Command::Stats {
+            input,
+            print,
+            output,
+            stats: args,
+        }) => {
+            let Some(datasets) = load(out, &input)? else {
+                return Ok(());
+            };
+            stats(out, &datasets, &args, &print, &open_output(&output)?)
+        }
+        Some(Command::Path {
+            from,
+            to,
+            input,
+            print,
+            similarity,
+        }) => {
+            let Some(datasets) = load(out, &input)? else {
+                return Ok(());
+            };
+            path(out, &datasets, &similarity, &from, &to, &print)?;
+            Ok(())
+        }
+    }
 }
 
-fn k_means_clustering<T: GraphData>(data: &[T], k: usize) -> HashMap<usize, Vec<&str>> {
-    // Extract features for k-means clustering
-    let features: Vec<Vec<f64>> = data
+// Read and filter the datasets as the input flags say and print how complete
+// they are, or with --list-categories print their categories instead and
+// return None
+fn load(out: &mut impl Write, input: &InputArgs) -> Result<Option<Datasets>, Box<dyn Error>> {
+    // Stdin can only be read once
+    let stdin_paths = input
+        .education
         .iter()
-        .map(|entry| {
-            (1..=10)
-                .map(|category| entry.get_weight(category).unwrap_or(0.0))
-                .collect()
-        })
-        .collect();
-
-    // Perform k-means clustering
-    let kmeans = KMeans::new(k);
-    let clusters = kmeans.fit(&features);
-
-    // Organize results into HashMap
-    let mut result_clusters: HashMap<usize, Vec<&str>> = HashMap::new();
-    for (i, cluster_idx) in clusters.iter().enumerate() {
-        result_clusters
-            .entry(*cluster_idx)
-            .or_insert_with(Vec::new)
-            .push(data[i].municipality());
+        .chain(&input.popgrowth)
+        .filter(|path| *path == "-")
+        .count();
+    if stdin_paths > 1 {
+        return Err("only one of --education and --popgrowth can read from stdin".into());
     }
 
-    result_clusters
-}
-
-fn filter_common_municipalities<T>(
-    data1: &[T],
-    data2: &[T],
-) -> Vec<(String, T, T)>
-where
-    T: Clone + PartialEq,
-{
-    let mut common_municipalities = Vec::new();
-
-    for entry1 in data1.iter() {
-        if let Some(entry2) = data2.iter().find(|&e| e == entry1) {
-            common_municipalities.push((
-                entry1.municipality().to_string(),
-                entry1.clone(),
-                entry2.clone(),
-            ));
-        }
+    let value_range = (input.min_value.is_some() || input.max_value.is_some()).then(|| {
+        input.min_value.unwrap_or(f64::NEG_INFINITY)..=input.max_value.unwrap_or(f64::INFINITY)
+    });
+    let config = InputConfig {
+        delimiter: input.delimiter,
+        duplicates: input.duplicates,
+        non_finite: input.non_finite,
+        education_key: Some(input.education_key.clone()),
+        pop_growth_key: Some(input.popgrowth_key.clone()),
+        education_buckets: input.education_buckets.clone(),
+        bucket_aggregate: input.bucket_aggregate,
+        value_range,
+        year: input.year,
+        name_normalization: if input.normalize_names {
+            NameNormalization::LOOSE
+        } else {
+            NameNormalization::EXACT
+        },
+        exclude_municipalities: input.exclude_municipality.clone(),
+        exclude_categories: input.exclude_category.clone(),
+        min_completeness: input.min_completeness,
+        common_only: input.common_only,
+        ..InputConfig::new(input.education.clone(), input.popgrowth.clone())
+    };
+
+    // Only list the categories in each file if asked to
+    if input.list_categories {
+        let datasets = Datasets::read(&config)?;
+        writeln!(
+            out,
+            "Education Categories ({}):",
+            input.education.join(", ")
+        )?;
+        print_category_counts(out, &datasets.education)?;
+        writeln!(
+            out,
+            "Pop Growth Categories ({}):",
+            input.popgrowth.join(", ")
+        )?;
+        print_category_counts(out, &datasets.pop_growth)?;
+        return Ok(None);
     }
 
-    common_municipalities
+    let datasets = Datasets::load(&config)?;
+    print_completeness(out, &datasets.education)?;
+    print_completeness(out, &datasets.pop_growth)?;
+    info!(
+        "analyzing {} education and {} pop growth records",
+        datasets.education.records.len(),
+        datasets.pop_growth.records.len()
+    );
+    Ok(Some(datasets))
 }
 
-// Function to plot clusters using plotters crate
-fn plot_clusters(clusters: &HashMap<usize, Vec<&str>>, file_path: &str) {
-    let root = BitMapBackend::new(file_path, (800, 600)).into_drawing_area();
-    root.fill(&WHITE).unwrap();
+// The stats command
+fn stats(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    args: &StatsArgs,
+    print: &PrintArgs,
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let top_n = match args.top_n[..] {
+        [category, n] => Some((i32::try_from(category)?, n)),
+        _ => None,
+    };
+    let config = StatsConfig {
+        inspect: args.inspect.clone(),
+        top_n,
+        histograms: args.histogram.clone(),
+        bins: args.bins,
+        precision: print.precision,
+    };
+    run_stats(out, datasets, &config, output)?;
+    Ok(())
+}
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Cluster Plot", ("sans-serif", 40).into_font())
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .build_ranged(0f64..10f64, 0f64..10f64)
-        .unwrap();
+// The graph command
+fn graph(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    similarity: &SimilarityArgs,
+    args: &GraphArgs,
+    print: &PrintArgs,
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let config = GraphsConfig {
+        graph: graph_config(similarity),
+        path: match &args.path[..] {
+            [from, to] => Some((from.clone(), to.clone())),
+            _ => None,
+        },
+        show_weights: args.show_weights,
+        mst: args.mst,
+        undirected: args.undirected,
+        precision: print.precision,
+    };
+    run_graphs(out, datasets, &config, output)?;
+    Ok(())
+}
 
-    for (_, points) in clusters {
-        let mut x_points = Vec::new();
-        let mut y_points = Vec::new();
+// The path command
+fn path(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    similarity: &SimilarityArgs,
+    from: &str,
+    to: &str,
+    print: &PrintArgs,
+) -> io::Result<()> {
+    print_paths(
+        out,
+        datasets,
+        &graph_config(similarity),
+        from,
+        to,
+        print.precision,
+    )
+}
 
-        for municipality in points {
-            if let Some((x, y)) = find_coordinates_for_municipality(municipality) {
-                x_points.push(x);
-                y_points.push(y);
-            }
-        }
+// The cluster command
+fn cluster(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    args: &ClusterArgs,
+    coordinates: Option<HashMap<String, (f64, f64)>>,
+    input: &InputArgs,
+    print: &PrintArgs,
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let config = ClusterConfig {
+        features: FeatureConfig {
+            standardize: !args.no_standardize,
+            weights: args.weights.clone(),
+            imputation: args.impute,
+            drop_constant: args.drop_constant,
+        },
+        k_means: KMeansConfig {
+            seed: args.seed,
+            n_init: args.n_init,
+            metric: args.metric,
+            max_iterations: args.max_iterations,
+            ..KMeansConfig::new(1)
+        },
+        clusters: args.clusters,
+        k_method: args.k_method,
+        gap_refs: args.gap_refs,
+        fuzzy: args.fuzzy,
+        fuzziness: args.fuzziness,
+        convergence: args.convergence,
+        coordinates,
+        geo_clusters: args.geo_clusters,
+        outlier_z: args.outlier_z,
+        eps: args.eps,
+        min_points: args.min_points,
+        linkage: args.linkage,
+        height: args.height,
+        classify: args.classify.clone(),
+        delimiter: input.delimiter,
+        distance_matrix: args.distance_matrix,
+        report: args.report,
+        log_scale: args.log_scale,
+        html: args.html,
+        progress: args.progress,
+        precision: print.precision,
+        ..ClusterConfig::new(args.algorithm)
+    };
+    run_clustering(out, datasets, &config, output)?;
+    Ok(())
+}
 
-        chart
-            .draw_series(
-                Points::of_element(
-                    x_points.iter().zip(y_points.iter()),
-                    5,
-                    &BLACK,
-                    &|c, s, st| {
-                        return EmptyElement::at(c)    // We want to construct a composed element on-the-fly
-                            +
-                            &Circle::new((2, 2), 5, BLUE.filled()), // Choose the marker style
-                            &|_| {},
-                        )
-                )
-                .unwrap();
+// The graph configuration of the similarity flags
+fn graph_config(similarity: &SimilarityArgs) -> GraphConfig {
+    GraphConfig {
+        min_correlation: similarity.min_correlation,
+        ..GraphConfig::with_default_threshold(similarity.threshold, similarity.measure)
     }
 }
 
-// Function to find coordinates for a municipality (example implementation)
-fn find_coordinates_for_municipality(municipality: &str) -> Option<(f64, f64)> {
-    // Replace this with a real implementation based on your data
-    // For example, you might have latitude and longitude data for municipalities
-    // Here, I'm just returning a random point for illustration purposes
-    let mut rng = rand::thread_rng();
-    Some((rng.gen_range(0.0..10.0), rng.gen_range(0.0..10.0)))
+// Resolve the output flags, creating the output directory when files are
+// going to be written
+fn open_output(args: &OutputArgs) -> io::Result<Output> {
+    let mode = if args.stats_only {
+        OutputMode::Skip
+    } else if args.dry_run {
+        OutputMode::List
+    } else {
+        OutputMode::Write
+    };
+    Output::new(mode, &args.out_dir, if args.svg { "svg" } else { "png" })
 }
-
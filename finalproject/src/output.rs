@@ -0,0 +1,167 @@
+use log::warn;
+use petgraph::graph::Graph;
+use petgraph::EdgeType;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    export_graphml, plot_histogram, render_dot_to_png, round_f64, save_graph_edgelist,
+    visualize_graph, AppError, Dataset, GraphData,
+};
+
+/// What happens to the output files of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Write every file.
+    Write,
+    /// List the paths of the files without writing them.
+    List,
+    /// Neither write nor list them.
+    Skip,
+}
+
+/// Where the files of a run go, and whether they are written at all.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// What happens to each file.
+    pub mode: OutputMode,
+    /// Directory the files are written to.
+    pub dir: PathBuf,
+    /// Format of the plots drawn with plotters, `png` or `svg`.
+    pub plot_ext: &'static str,
+}
+
+impl Output {
+    /// Create an output of `mode` in `dir`, creating the directory when files
+    /// are going to be written.
+    pub fn new(
+        mode: OutputMode,
+        dir: impl AsRef<Path>,
+        plot_ext: &'static str,
+    ) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        if mode == OutputMode::Write {
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(Output {
+            mode,
+            dir,
+            plot_ext,
+        })
+    }
+
+    /// The path of `file_name` in the output directory.
+    pub fn path(&self, file_name: &str) -> String {
+        self.dir.join(file_name).to_string_lossy().into_owned()
+    }
+
+    /// Write one output file with `write`, logging a failure as a warning
+    /// without stopping the run. In [`OutputMode::List`] the path is only
+    /// printed to `out`, and in [`OutputMode::Skip`] nothing happens at all.
+    /// Returns whether the file was (or would have been) written.
+    pub fn write(
+        &self,
+        out: &mut impl Write,
+        file_name: &str,
+        write: impl FnOnce(&str) -> Result<(), AppError>,
+    ) -> io::Result<bool> {
+        let path = self.path(file_name);
+        match self.mode {
+            OutputMode::Write => {}
+            OutputMode::List => {
+                writeln!(out, "Would write {}", path)?;
+                return Ok(true);
+            }
+            OutputMode::Skip => return Ok(false),
+        }
+        match write(&path) {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                warn!("cannot write {}: {}", path, err);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Write the plot `name` in the format of the run, like [`Output::write`].
+    pub fn plot(
+        &self,
+        out: &mut impl Write,
+        name: &str,
+        write: impl FnOnce(&str) -> Result<(), AppError>,
+    ) -> io::Result<bool> {
+        self.write(out, &format!("{}.{}", name, self.plot_ext), write)
+    }
+
+    /// Plot the distribution of `category` in `dataset` as
+    /// `<name>_histogram_<category>`, returning whether the dataset has the
+    /// category at all.
+    pub fn write_histogram<T: GraphData>(
+        &self,
+        out: &mut impl Write,
+        dataset: &Dataset<T>,
+        name: &str,
+        category: i32,
+        bins: usize,
+    ) -> io::Result<bool> {
+        if !dataset.categories().contains(&category) {
+            return Ok(false);
+        }
+        self.plot(out, &format!("{}_histogram_{}", name, category), |path| {
+            plot_histogram(&dataset.records, category, bins, &dataset.key, path)
+        })?;
+        Ok(true)
+    }
+
+    /// Write `<name>.dot`, render it to `<name>.png` and write
+    /// `<name>.graphml` and the `<name>.tsv` edge list, with the weights
+    /// rounded to `precision` decimals.
+    pub fn write_graph<Ty: EdgeType>(
+        &self,
+        out: &mut impl Write,
+        graph: &Graph<&str, f64, Ty>,
+        name: &str,
+        show_weights: bool,
+        precision: usize,
+    ) -> io::Result<()> {
+        let graph = &graph.map(|_, &name| name, |_, &weight| round_f64(weight, precision));
+        if self.write(out, &format!("{}.dot", name), |path| {
+            visualize_graph(graph, path, show_weights)
+        })? {
+            let dot_path = self.path(&format!("{}.dot", name));
+            self.write(out, &format!("{}.png", name), |path| {
+                render_dot_to_png(&dot_path, path)
+            })?;
+        }
+        self.write(out, &format!("{}.graphml", name), |path| {
+            export_graphml(graph, path)
+        })?;
+        self.write(out, &format!("{}.tsv", name), |path| {
+            save_graph_edgelist(graph, path)
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_writes_the_paths_to_the_writer_without_creating_files() {
+        let output = Output::new(OutputMode::List, "listing_dir", "svg").unwrap();
+        let mut out = Vec::new();
+        let written = output
+            .plot(&mut out, "sizes", |_| {
+                panic!("listing must not write files")
+            })
+            .unwrap();
+        assert!(written);
+        let listing = String::from_utf8(out).unwrap();
+        assert_eq!(
+            listing.trim(),
+            format!("Would write {}", output.path("sizes.svg"))
+        );
+        assert!(!Path::new("listing_dir").exists());
+    }
+}
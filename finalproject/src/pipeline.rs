@@ -0,0 +1,913 @@
+use log::{info, warn};
+use petgraph::graph::DiGraph;
+use petgraph::Direction;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::{
+    all_categories, betweenness_centrality, bucketize, canonicalize_assignments, completeness,
+    connected_components, constant_categories, correlation_by_municipality, create_cross_graph,
+    create_graph_with, create_ugraph_with, degree_distribution, exclude, extract_features_with,
+    filter_by_year, filter_common_municipalities_with, fmt_f64, handle_non_finite, imputed_values,
+    joint_features, node_strength, only_in_first_with, only_in_second_with, pagerank,
+    plot_degree_distribution, print_components, print_path, print_record, print_summary,
+    print_top_n, print_top_ranks, print_trends, read_bucket_mapping, read_category_names,
+    read_inputs, restrict_to_common, round_f64, spanning_tree, standardize, standardize_point,
+    validate, visualize_graph, weight_features, Aggregate, AppError, Delimiter, DuplicatePolicy,
+    EducationData, FromParts, GraphConfig, GraphData, Imputation, NameNormalization,
+    NonFinitePolicy, Output, PopGrowthData,
+};
+
+/// How the education and population growth files are read and filtered
+/// before the analysis.
+#[derive(Debug, Clone)]
+pub struct InputConfig {
+    /// Education data files, combined into one dataset; `-` reads stdin.
+    pub education: Vec<String>,
+    /// Population growth data files, combined likewise.
+    pub pop_growth: Vec<String>,
+    /// Split every file on this, whatever its extension.
+    pub delimiter: Option<Delimiter>,
+    /// What to do with repeated municipalities in one dataset.
+    pub duplicates: DuplicatePolicy,
+    /// What to do with `NaN` and infinite weights.
+    pub non_finite: NonFinitePolicy,
+    /// Key file naming the education categories; a file that doesn't exist
+    /// names none.
+    pub education_key: Option<String>,
+    /// Key file naming the population growth categories, likewise.
+    pub pop_growth_key: Option<String>,
+    /// File of `category; bucket` lines collapsing the education categories
+    /// with [`bucketize`].
+    pub education_buckets: Option<String>,
+    /// How bucketed categories are combined.
+    pub bucket_aggregate: Aggregate,
+    /// Fail with [`AppError::OutOfRange`] on weights outside this range.
+    pub value_range: Option<RangeInclusive<f64>>,
+    /// Keep only the values measured in this year.
+    pub year: Option<i32>,
+    /// How names are compared when pairing the municipalities of the datasets.
+    pub name_normalization: NameNormalization,
+    /// Municipalities left out, named ignoring case.
+    pub exclude_municipalities: Vec<String>,
+    /// Categories left out of both datasets.
+    pub exclude_categories: Vec<i32>,
+    /// Drop the municipalities with less than this fraction of their
+    /// dataset's categories.
+    pub min_completeness: Option<f64>,
+    /// Keep only the municipalities present in both datasets.
+    pub common_only: bool,
+}
+
+impl InputConfig {
+    /// Read the `education` and `pop_growth` files without category names,
+    /// failing on duplicate municipalities and non-finite values and
+    /// filtering nothing out.
+    pub fn new(education: Vec<String>, pop_growth: Vec<String>) -> Self {
+        InputConfig {
+            education,
+            pop_growth,
+            delimiter: None,
+            duplicates: DuplicatePolicy::Error,
+            non_finite: NonFinitePolicy::Error,
+            education_key: None,
+            pop_growth_key: None,
+            education_buckets: None,
+            bucket_aggregate: Aggregate::Sum,
+            value_range: None,
+            year: None,
+            name_normalization: NameNormalization::EXACT,
+            exclude_municipalities: Vec::new(),
+            exclude_categories: Vec::new(),
+            min_completeness: None,
+            common_only: false,
+        }
+    }
+}
+
+/// How many municipalities of a dataset have every one of its categories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completeness {
+    /// Municipalities with all of the categories.
+    pub complete: usize,
+    /// Municipalities in the dataset.
+    pub municipalities: usize,
+    /// Categories in the dataset.
+    pub categories: usize,
+}
+
+/// How a dataset's categories become the features it is clustered on.
+#[derive(Debug, Clone)]
+pub struct FeatureConfig {
+    /// Z-score each category with [`standardize`], so large-scale categories
+    /// don't dominate the distances.
+    pub standardize: bool,
+    /// Weights multiplying the categories in ascending category order;
+    /// categories without one count once.
+    pub weights: Vec<f64>,
+    /// How missing categories are filled in.
+    pub imputation: Imputation,
+    /// Leave out the categories with the same value for every municipality.
+    pub drop_constant: bool,
+}
+
+/// A dataset's clustering features and the categories they come from.
+#[derive(Debug, Clone)]
+pub struct Features {
+    /// The categories in ascending order, one per feature dimension.
+    pub categories: Vec<i32>,
+    /// The names of the categories, for reports.
+    pub names: Vec<String>,
+    /// One feature vector per record, in input order.
+    pub values: Vec<Vec<f64>>,
+}
+
+/// One dataset, read and filtered for the analysis.
+#[derive(Debug, Clone)]
+pub struct Dataset<T> {
+    /// The name the reports give the dataset, "Education" or "Pop Growth".
+    pub label: &'static str,
+    /// The records, in input order.
+    pub records: Vec<T>,
+    /// The category names of the key file, if there is one.
+    pub key: HashMap<i32, String>,
+    /// How complete the records were before [`InputConfig::min_completeness`]
+    /// dropped any.
+    pub completeness: Completeness,
+}
+
+impl<T: GraphData> Dataset<T> {
+    /// Every category of the records, sorted.
+    pub fn categories(&self) -> Vec<i32> {
+        all_categories(&self.records)
+    }
+
+    /// The municipality of every record, in input order.
+    pub fn municipalities(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .map(|entry| entry.municipality().to_string())
+            .collect()
+    }
+
+    /// One name per category from the key, falling back to "Category N" for
+    /// categories it doesn't name.
+    pub fn category_names(&self, categories: &[i32]) -> Vec<String> {
+        categories
+            .iter()
+            .map(|category| match self.key.get(category) {
+                Some(name) => name.clone(),
+                None => format!("Category {}", category),
+            })
+            .collect()
+    }
+
+    /// The features the records are clustered on, as `config` says, after
+    /// warning about the categories too constant to separate municipalities.
+    pub fn features(&self, config: &FeatureConfig) -> Features {
+        let categories = self.clustering_categories(config.drop_constant);
+        let fill = imputed_values(&self.records, &categories, config.imputation);
+        let mut values = extract_features_with(&self.records, &categories, &fill);
+        if config.standardize {
+            standardize(&mut values);
+        }
+        weight_features(&mut values, &config.weights);
+        Features {
+            names: self.category_names(&categories),
+            categories,
+            values,
+        }
+    }
+
+    /// The features of new `records`, prepared like the [`Dataset::features`]
+    /// built with `config`, filling in and standardizing against this
+    /// dataset's records.
+    pub fn classification_features(
+        &self,
+        records: &[T],
+        features: &Features,
+        config: &FeatureConfig,
+    ) -> Vec<Vec<f64>> {
+        let fill = imputed_values(&self.records, &features.categories, config.imputation);
+        let reference = extract_features_with(&self.records, &features.categories, &fill);
+        let mut values = extract_features_with(records, &features.categories, &fill);
+        if config.standardize {
+            for point in &mut values {
+                standardize_point(&reference, point);
+            }
+        }
+        weight_features(&mut values, &config.weights);
+        values
+    }
+
+    /// Municipality names per cluster of per-record `labels`, leaving out the
+    /// records labeled as noise, with the clusters numbered by their first
+    /// member.
+    pub fn group_by_cluster(&self, labels: &[Option<usize>]) -> HashMap<usize, Vec<String>> {
+        let mut clusters: HashMap<usize, Vec<String>> = HashMap::new();
+        for (entry, label) in self.records.iter().zip(labels) {
+            if let Some(cluster) = label {
+                clusters
+                    .entry(*cluster)
+                    .or_default()
+                    .push(entry.municipality().to_string());
+            }
+        }
+        canonicalize_assignments(clusters)
+    }
+
+    /// Sorted names of the municipalities whose records `labels` marks as noise.
+    pub fn noise(&self, labels: &[Option<usize>]) -> Vec<&str> {
+        let mut noise: Vec<&str> = self
+            .records
+            .iter()
+            .zip(labels)
+            .filter(|(_, label)| label.is_none())
+            .map(|(entry, _)| entry.municipality())
+            .collect();
+        noise.sort_unstable();
+        noise
+    }
+
+    /// The directed similarity graph of the records built with `config`,
+    /// logging its size.
+    pub fn similarity_graph(&self, config: &GraphConfig) -> DiGraph<&str, f64> {
+        let graph = create_graph_with(&self.records, config);
+        info!(
+            "{} graph: {} nodes, {} edges",
+            self.label.to_lowercase(),
+            graph.node_count(),
+            graph.edge_count()
+        );
+        graph
+    }
+
+    // The categories to cluster on, warning about the constant ones and
+    // leaving them out if `drop` is set
+    fn clustering_categories(&self, drop: bool) -> Vec<i32> {
+        let categories = self.categories();
+        let constant = constant_categories(&self.records, &categories);
+        for category in &constant {
+            let name = self
+                .key
+                .get(category)
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default();
+            warn!(
+                "{} category {}{} has the same value for every municipality{}",
+                self.label,
+                category,
+                name,
+                if drop { ", dropping it" } else { "" }
+            );
+        }
+        if drop {
+            categories
+                .into_iter()
+                .filter(|category| !constant.contains(category))
+                .collect()
+        } else {
+            categories
+        }
+    }
+}
+
+impl<T> Dataset<T>
+where
+    T: for<'de> Deserialize<'de> + FromParts + GraphData,
+{
+    // Read and combine `paths`, dropping what `config` excludes
+    fn read(
+        label: &'static str,
+        paths: &[String],
+        key: Option<&str>,
+        config: &InputConfig,
+    ) -> Result<Self, AppError> {
+        let mut records = read_inputs::<T>(paths, config.delimiter, config.duplicates)?;
+        if !config.exclude_municipalities.is_empty() || !config.exclude_categories.is_empty() {
+            records = exclude(
+                &records,
+                &config.exclude_municipalities,
+                &config.exclude_categories,
+            );
+        }
+        let key = match key {
+            Some(path) if Path::new(path).exists() => read_category_names(path)?,
+            _ => HashMap::new(),
+        };
+        Ok(Dataset {
+            label,
+            completeness: completeness_of(&records),
+            records,
+            key,
+        })
+    }
+
+    // Apply the non-finite policy and the year filter of `config`
+    fn clean(&mut self, config: &InputConfig) -> Result<(), AppError> {
+        self.records = handle_non_finite(std::mem::take(&mut self.records), config.non_finite)?;
+        if let Some(year) = config.year {
+            self.records = filter_by_year(&self.records, year);
+        }
+        Ok(())
+    }
+
+    // Fail with every weight outside `range` listed
+    fn check_range(&self, range: RangeInclusive<f64>) -> Result<(), AppError> {
+        let (min, max) = (*range.start(), *range.end());
+        validate(&self.records, &self.categories(), range).map_err(|values| AppError::OutOfRange {
+            dataset: self.label.to_lowercase(),
+            min,
+            max,
+            values,
+        })
+    }
+
+    // Record how complete the records are, then with `min` keep only the
+    // municipalities with at least that fraction of the categories
+    fn drop_incomplete(&mut self, min: Option<f64>) -> Result<(), AppError> {
+        self.completeness = completeness_of(&self.records);
+        let Some(min) = min else {
+            return Ok(());
+        };
+        let fractions = completeness(&self.records, &self.categories());
+        let before = self.records.len();
+        self.records
+            .retain(|entry| fractions[entry.municipality()] >= min);
+        if self.records.len() < before {
+            warn!(
+                "dropping {} {} municipalities with less than {}% of the categories",
+                before - self.records.len(),
+                self.label.to_lowercase(),
+                min * 100.0
+            );
+        }
+        if self.records.is_empty() {
+            return Err(AppError::Incomplete(self.label.to_lowercase()));
+        }
+        Ok(())
+    }
+}
+
+// How many of the municipalities of `data` have all of its categories
+fn completeness_of<T: GraphData>(data: &[T]) -> Completeness {
+    let categories = all_categories(data);
+    Completeness {
+        complete: completeness(data, &categories)
+            .values()
+            .filter(|&&fraction| fraction >= 1.0)
+            .count(),
+        municipalities: data.len(),
+        categories: categories.len(),
+    }
+}
+
+/// Features over both datasets for the municipalities present in both, as
+/// built by [`joint_features`].
+#[derive(Debug, Clone)]
+pub struct JointFeatures {
+    /// The common municipalities, in education order.
+    pub municipalities: Vec<String>,
+    /// The names of the feature dimensions, prefixed by their dataset's label.
+    pub names: Vec<String>,
+    /// One feature vector per municipality.
+    pub values: Vec<Vec<f64>>,
+}
+
+/// The education and population growth datasets of one analysis.
+#[derive(Debug, Clone)]
+pub struct Datasets {
+    /// The education dataset.
+    pub education: Dataset<EducationData>,
+    /// The population growth dataset.
+    pub pop_growth: Dataset<PopGrowthData>,
+    /// How names are compared when pairing the municipalities of the datasets.
+    pub name_normalization: NameNormalization,
+}
+
+impl Datasets {
+    /// Read both datasets and their category keys as `config` says, leaving
+    /// out the excluded municipalities and categories but filtering nothing
+    /// else.
+    pub fn read(config: &InputConfig) -> Result<Self, AppError> {
+        Ok(Datasets {
+            education: Dataset::read(
+                "Education",
+                &config.education,
+                config.education_key.as_deref(),
+                config,
+            )?,
+            pop_growth: Dataset::read(
+                "Pop Growth",
+                &config.pop_growth,
+                config.pop_growth_key.as_deref(),
+                config,
+            )?,
+            name_normalization: config.name_normalization,
+        })
+    }
+
+    /// Read both datasets, then clean and filter them as `config` says: deal
+    /// with non-finite values, keep one year, check the value range, bucket
+    /// the education categories, restrict both to their common municipalities
+    /// and drop the incomplete ones.
+    pub fn load(config: &InputConfig) -> Result<Self, AppError> {
+        let mut datasets = Self::read(config)?;
+        datasets.education.clean(config)?;
+        datasets.pop_growth.clean(config)?;
+
+        if let Some(range) = &config.value_range {
+            datasets.education.check_range(range.clone())?;
+            datasets.pop_growth.check_range(range.clone())?;
+        }
+
+        if let Some(path) = &config.education_buckets {
+            let mapping = read_bucket_mapping(path)?;
+            datasets.education.records = bucketize(
+                &datasets.education.records,
+                &mapping,
+                config.bucket_aggregate,
+            );
+        }
+
+        if config.common_only {
+            (datasets.education.records, datasets.pop_growth.records) = restrict_to_common(
+                &datasets.education.records,
+                &datasets.pop_growth.records,
+                config.name_normalization,
+            );
+            if datasets.education.records.is_empty() {
+                return Err(AppError::NoCommonMunicipalities);
+            }
+        }
+
+        datasets
+            .education
+            .drop_incomplete(config.min_completeness)?;
+        datasets
+            .pop_growth
+            .drop_incomplete(config.min_completeness)?;
+        Ok(datasets)
+    }
+
+    /// Pair up the records of the municipalities in both datasets, named as
+    /// in the education dataset.
+    pub fn common_municipalities(&self) -> Vec<(String, EducationData, PopGrowthData)> {
+        filter_common_municipalities_with(
+            &self.education.records,
+            &self.pop_growth.records,
+            self.name_normalization,
+        )
+    }
+
+    /// The municipalities only the education dataset has.
+    pub fn only_in_education(&self) -> Vec<String> {
+        only_in_first_with(
+            &self.education.records,
+            &self.pop_growth.records,
+            self.name_normalization,
+        )
+    }
+
+    /// The municipalities only the population growth dataset has.
+    pub fn only_in_pop_growth(&self) -> Vec<String> {
+        only_in_second_with(
+            &self.education.records,
+            &self.pop_growth.records,
+            self.name_normalization,
+        )
+    }
+
+    /// The joint features of the common municipalities over the categories
+    /// of both `education` and `pop_growth` features, standardized if
+    /// `standardize` is set.
+    pub fn joint_features(
+        &self,
+        education: &Features,
+        pop_growth: &Features,
+        standardize_features: bool,
+    ) -> JointFeatures {
+        let mut categories = education.categories.clone();
+        categories.extend(&pop_growth.categories);
+        categories.sort_unstable();
+        categories.dedup();
+        let (municipalities, mut values) = joint_features(
+            &self.education.records,
+            &self.pop_growth.records,
+            &categories,
+        );
+        if standardize_features {
+            standardize(&mut values);
+        }
+        let names = self
+            .education
+            .category_names(&categories)
+            .into_iter()
+            .map(|name| format!("{}: {}", self.education.label, name))
+            .chain(
+                self.pop_growth
+                    .category_names(&categories)
+                    .into_iter()
+                    .map(|name| format!("{}: {}", self.pop_growth.label, name)),
+            )
+            .collect();
+        JointFeatures {
+            municipalities,
+            names,
+            values,
+        }
+    }
+
+    /// The municipalities of either dataset that have `coordinates`, each
+    /// once and in input order, with their coordinates.
+    pub fn located(
+        &self,
+        coordinates: &HashMap<String, (f64, f64)>,
+    ) -> (Vec<String>, Vec<(f64, f64)>) {
+        let mut located: Vec<String> = Vec::new();
+        for name in self
+            .education
+            .municipalities()
+            .into_iter()
+            .chain(self.pop_growth.municipalities())
+        {
+            if coordinates.contains_key(&name) && !located.contains(&name) {
+                located.push(name);
+            }
+        }
+        let points = located.iter().map(|name| coordinates[name]).collect();
+        (located, points)
+    }
+}
+
+/// What the stats step prints and plots besides the summaries.
+#[derive(Debug, Clone)]
+pub struct StatsConfig {
+    /// Print every category of the municipality with this name (ignoring case).
+    pub inspect: Option<String>,
+    /// Print the `n` municipalities with the largest weight of a category, as
+    /// `(category, n)`.
+    pub top_n: Option<(i32, usize)>,
+    /// Plot the distribution of each of these categories in each dataset that
+    /// has it.
+    pub histograms: Vec<i32>,
+    /// Number of bins of the histograms.
+    pub bins: usize,
+    /// Decimal places of every number printed.
+    pub precision: usize,
+}
+
+/// The stats step: print the records and rankings asked for and the
+/// summaries, overlap, correlations and trends of both datasets, and plot the
+/// histograms asked for through `output`.
+pub fn run_stats(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    config: &StatsConfig,
+    output: &Output,
+) -> io::Result<()> {
+    let (education, pop_growth) = (&datasets.education, &datasets.pop_growth);
+    let precision = config.precision;
+
+    // Show one municipality's records in full if requested
+    if let Some(name) = &config.inspect {
+        print_record(out, education, name, precision)?;
+        print_record(out, pop_growth, name, precision)?;
+    }
+
+    // Rank municipalities by one category if asked
+    if let Some((category, n)) = config.top_n {
+        print_top_n(out, education, category, n, precision)?;
+        print_top_n(out, pop_growth, category, n, precision)?;
+    }
+
+    // Plot the distribution of chosen categories if asked
+    for &category in &config.histograms {
+        let in_education =
+            output.write_histogram(out, education, "education", category, config.bins)?;
+        let in_pop_growth =
+            output.write_histogram(out, pop_growth, "pop_growth", category, config.bins)?;
+        if !in_education && !in_pop_growth {
+            warn!(
+                "neither dataset has category {}, skipping its histogram",
+                category
+            );
+        }
+    }
+
+    print_summary(out, education, precision)?;
+    print_summary(out, pop_growth, precision)?;
+
+    // Compare the municipalities the datasets cover
+    writeln!(
+        out,
+        "Common municipalities: {}",
+        datasets.common_municipalities().len()
+    )?;
+    let only_education = datasets.only_in_education();
+    let only_pop_growth = datasets.only_in_pop_growth();
+    writeln!(
+        out,
+        "{} municipalities only in education data: {}",
+        only_education.len(),
+        only_education.join(", ")
+    )?;
+    writeln!(
+        out,
+        "{} municipalities only in pop growth data: {}",
+        only_pop_growth.len(),
+        only_pop_growth.join(", ")
+    )?;
+
+    // Correlate education with pop growth for each common municipality
+    let correlations = correlation_by_municipality(&education.records, &pop_growth.records);
+    let mut correlated: Vec<(&String, &f64)> = correlations.iter().collect();
+    correlated.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    writeln!(out, "Education/Pop Growth Correlations:")?;
+    for (municipality, r) in correlated {
+        writeln!(out, "  {}: {}", municipality, fmt_f64(*r, precision))?;
+    }
+
+    // Rank municipalities by how their values change over the years
+    print_trends(out, education, precision)?;
+    print_trends(out, pop_growth, precision)
+}
+
+/// How the graph step builds, analyzes and writes the similarity graphs.
+#[derive(Debug, Clone)]
+pub struct GraphsConfig {
+    /// Which municipalities are linked, and how the edges are weighted.
+    pub graph: GraphConfig,
+    /// Print the cheapest path between these two municipalities.
+    pub path: Option<(String, String)>,
+    /// Label the edges of the DOT graphs with their weights.
+    pub show_weights: bool,
+    /// Also write the spanning tree of each similarity graph.
+    pub mst: bool,
+    /// Write the graphs with one undirected edge per linked pair.
+    pub undirected: bool,
+    /// Decimal places of every number printed or written.
+    pub precision: usize,
+}
+
+/// The graph step: build the similarity graphs, print their components,
+/// central municipalities and the path asked for, then write the graphs and
+/// their plots through `output`.
+pub fn run_graphs(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    config: &GraphsConfig,
+    output: &Output,
+) -> io::Result<()> {
+    let (education, pop_growth) = (&datasets.education, &datasets.pop_growth);
+    let (graph_config, precision) = (&config.graph, config.precision);
+    let education_graph = education.similarity_graph(graph_config);
+    let pop_growth_graph = pop_growth.similarity_graph(graph_config);
+
+    // Summarize the groups of municipalities linked in each graph
+    print_components(out, "Education", &connected_components(&education_graph))?;
+    print_components(out, "Pop Growth", &connected_components(&pop_growth_graph))?;
+
+    // Rank the most central municipalities of each graph
+    print_top_ranks(
+        out,
+        "Education PageRank",
+        &pagerank(&education_graph, 0.85, 100, graph_config),
+        precision,
+    )?;
+    print_top_ranks(
+        out,
+        "Pop Growth PageRank",
+        &pagerank(&pop_growth_graph, 0.85, 100, graph_config),
+        precision,
+    )?;
+
+    // Show how two municipalities are linked if asked
+    if let Some((from, to)) = &config.path {
+        print_path(
+            out,
+            "Education",
+            &education_graph,
+            from,
+            to,
+            graph_config,
+            precision,
+        )?;
+        print_path(
+            out,
+            "Pop Growth",
+            &pop_growth_graph,
+            from,
+            to,
+            graph_config,
+            precision,
+        )?;
+    }
+
+    // Find the municipalities bridging separate groups
+    print_top_ranks(
+        out,
+        "Education Betweenness",
+        &betweenness_centrality(&education_graph),
+        precision,
+    )?;
+    print_top_ranks(
+        out,
+        "Pop Growth Betweenness",
+        &betweenness_centrality(&pop_growth_graph),
+        precision,
+    )?;
+
+    // Find the municipalities most alike to their neighbors altogether
+    print_top_ranks(
+        out,
+        "Education Strength",
+        &node_strength(&education_graph, graph_config),
+        precision,
+    )?;
+    print_top_ranks(
+        out,
+        "Pop Growth Strength",
+        &node_strength(&pop_growth_graph, graph_config),
+        precision,
+    )?;
+
+    // Plot how many neighbors the municipalities have; the graphs link both ways,
+    // so out-degrees equal in-degrees
+    output.plot(out, "education_degrees", |path| {
+        plot_degree_distribution(
+            &degree_distribution(&education_graph, Direction::Outgoing),
+            "Out-degree",
+            path,
+        )
+    })?;
+    output.plot(out, "pop_growth_degrees", |path| {
+        plot_degree_distribution(
+            &degree_distribution(&pop_growth_graph, Direction::Outgoing),
+            "Out-degree",
+            path,
+        )
+    })?;
+
+    // Visualize the graphs and export them as GraphML for tools like Gephi and Cytoscape
+    let show_weights = config.show_weights;
+    if config.undirected {
+        output.write_graph(
+            out,
+            &create_ugraph_with(&education.records, graph_config),
+            "education_graph",
+            show_weights,
+            precision,
+        )?;
+        output.write_graph(
+            out,
+            &create_ugraph_with(&pop_growth.records, graph_config),
+            "pop_growth_graph",
+            show_weights,
+            precision,
+        )?;
+    } else {
+        output.write_graph(
+            out,
+            &education_graph,
+            "education_graph",
+            show_weights,
+            precision,
+        )?;
+        output.write_graph(
+            out,
+            &pop_growth_graph,
+            "pop_growth_graph",
+            show_weights,
+            precision,
+        )?;
+    }
+
+    // Write the spanning tree of each similarity graph if requested
+    if config.mst {
+        output.write_graph(
+            out,
+            &spanning_tree(
+                &create_ugraph_with(&education.records, graph_config),
+                graph_config,
+            ),
+            "education_mst",
+            show_weights,
+            precision,
+        )?;
+        output.write_graph(
+            out,
+            &spanning_tree(
+                &create_ugraph_with(&pop_growth.records, graph_config),
+                graph_config,
+            ),
+            "pop_growth_mst",
+            show_weights,
+            precision,
+        )?;
+    }
+
+    // Link each common municipality's education and pop growth profiles
+    let cross_graph = create_cross_graph(&education.records, &pop_growth.records).map(
+        |_, name| name.clone(),
+        |_, &weight| round_f64(weight, precision),
+    );
+    output.write(out, "cross_graph.dot", |path| {
+        visualize_graph(&cross_graph, path, show_weights)
+    })?;
+    Ok(())
+}
+
+/// The path step: print the cheapest path from `from` to `to` in each
+/// similarity graph built with `config`.
+pub fn print_paths(
+    out: &mut impl Write,
+    datasets: &Datasets,
+    config: &GraphConfig,
+    from: &str,
+    to: &str,
+    precision: usize,
+) -> io::Result<()> {
+    print_path(
+        out,
+        "Education",
+        &datasets.education.similarity_graph(config),
+        from,
+        to,
+        config,
+        precision,
+    )?;
+    print_path(
+        out,
+        "Pop Growth",
+        &datasets.pop_growth.similarity_graph(config),
+        from,
+        to,
+        config,
+        precision,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_file;
+    use crate::{k_means_features, KMeansConfig};
+
+    #[test]
+    fn common_only_clusters_both_datasets_on_the_same_municipalities() {
+        let education = temp_file(
+            "common-education.json",
+            r#"[
+                {"municipality": "Alpha", "data": {"1": [2020, 1.0]}},
+                {"municipality": "Beta", "data": {"1": [2020, 2.0]}},
+                {"municipality": "Gamma", "data": {"1": [2020, 9.0]}},
+                {"municipality": "Delta", "data": {"1": [2020, 10.0]}}
+            ]"#,
+        );
+        let pop_growth = temp_file(
+            "common-pop-growth.json",
+            r#"[
+                {"municipality": "Delta", "data": {"1": [2020, -1.0]}},
+                {"municipality": "Gamma", "data": {"1": [2020, 0.5]}},
+                {"municipality": "Beta", "data": {"1": [2020, 3.0]}},
+                {"municipality": "Epsilon", "data": {"1": [2020, 4.0]}}
+            ]"#,
+        );
+        let config = InputConfig {
+            common_only: true,
+            ..InputConfig::new(vec![education.clone()], vec![pop_growth.clone()])
+        };
+        let datasets = Datasets::load(&config);
+        std::fs::remove_file(education).unwrap();
+        std::fs::remove_file(pop_growth).unwrap();
+        let datasets = datasets.unwrap();
+
+        let feature_config = FeatureConfig {
+            standardize: true,
+            weights: Vec::new(),
+            imputation: Imputation::Zero,
+            drop_constant: false,
+        };
+        let clustered = |municipalities: Vec<String>, features: Features| {
+            let result =
+                k_means_features(&municipalities, &features.values, &KMeansConfig::new(2)).unwrap();
+            let mut members: Vec<String> = result.assignments.into_values().flatten().collect();
+            members.sort_unstable();
+            members
+        };
+        let education = clustered(
+            datasets.education.municipalities(),
+            datasets.education.features(&feature_config),
+        );
+        let pop_growth = clustered(
+            datasets.pop_growth.municipalities(),
+            datasets.pop_growth.features(&feature_config),
+        );
+        assert_eq!(education, ["Beta", "Delta", "Gamma"]);
+        assert_eq!(pop_growth, education);
+    }
+}
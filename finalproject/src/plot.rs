@@ -0,0 +1,816 @@
+use log::warn;
+use plotters::coord::ranged1d::{AsRangedCoord, ValueFormatter};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+
+use crate::{histogram, AppError, GraphData};
+
+/// Scatter plot of the clusters, drawing each municipality at its entry in
+/// `coordinates` and each cluster in its own color. Municipalities without
+/// coordinates are skipped with a warning. `x_desc` and `y_desc` label the axes.
+/// Paths ending in `.svg` get an SVG file, anything else a PNG.
+pub fn plot_clusters(
+    clusters: &HashMap<usize, Vec<String>>,
+    coordinates: &HashMap<String, (f64, f64)>,
+    x_desc: &str,
+    y_desc: &str,
+    file_path: &str,
+) -> Result<(), AppError> {
+    if file_path.ends_with(".svg") {
+        let root = SVGBackend::new(file_path, (800, 600)).into_drawing_area();
+        draw_clusters(&root, clusters, coordinates, x_desc, y_desc)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(file_path, (800, 600)).into_drawing_area();
+        draw_clusters(&root, clusters, coordinates, x_desc, y_desc)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+/// Draw the scatter plot of `plot_clusters` onto any drawing area, so callers
+/// can pick the backend or lay several plots out on one canvas.
+pub fn draw_clusters<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    clusters: &HashMap<usize, Vec<String>>,
+    coordinates: &HashMap<String, (f64, f64)>,
+    x_desc: &str,
+    y_desc: &str,
+) -> Result<(), AppError> {
+    draw_cluster_chart(
+        root,
+        "Cluster Plot",
+        clusters,
+        coordinates,
+        x_desc,
+        y_desc,
+        true,
+    )
+}
+
+/// One scatter of [`plot_clusters_side_by_side`].
+pub struct ClusterPanel<'a> {
+    /// Caption above the scatter.
+    pub title: &'a str,
+    /// Municipality names per cluster index.
+    pub clusters: &'a HashMap<usize, Vec<String>>,
+    /// Where to draw each municipality.
+    pub coordinates: &'a HashMap<String, (f64, f64)>,
+}
+
+/// Draw one cluster scatter per panel next to each other in a single image,
+/// such as education and pop growth clusters of the same municipalities, with
+/// one color legend below them all. Paths ending in `.svg` get an SVG file,
+/// anything else a PNG.
+pub fn plot_clusters_side_by_side(
+    panels: &[ClusterPanel],
+    x_desc: &str,
+    y_desc: &str,
+    file_path: &str,
+) -> Result<(), AppError> {
+    let size = (800 * panels.len().max(1) as u32, 650);
+    if file_path.ends_with(".svg") {
+        let root = SVGBackend::new(file_path, size).into_drawing_area();
+        draw_side_by_side(&root, panels, x_desc, y_desc)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(file_path, size).into_drawing_area();
+        draw_side_by_side(&root, panels, x_desc, y_desc)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+// The panels of `plot_clusters_side_by_side` above a 50 pixel legend strip
+fn draw_side_by_side<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    panels: &[ClusterPanel],
+    x_desc: &str,
+    y_desc: &str,
+) -> Result<(), AppError> {
+    root.fill(&WHITE)?;
+    let (plots, legend) = root.split_vertically(root.dim_in_pixel().1 - 50);
+
+    for (area, panel) in plots
+        .split_evenly((1, panels.len().max(1)))
+        .iter()
+        .zip(panels)
+    {
+        draw_cluster_chart(
+            area,
+            panel.title,
+            panel.clusters,
+            panel.coordinates,
+            x_desc,
+            y_desc,
+            false,
+        )?;
+    }
+
+    let cluster_ids: BTreeSet<usize> = panels
+        .iter()
+        .flat_map(|panel| panel.clusters.keys().copied())
+        .collect();
+    for (i, &cluster) in cluster_ids.iter().enumerate() {
+        let (x, y) = (60 + 120 * i as i32, 25);
+        legend.draw(&Circle::new((x, y), 5, cluster_color(cluster).filled()))?;
+        legend.draw(&Text::new(
+            format!("Cluster {}", cluster),
+            (x + 12, y - 9),
+            ("sans-serif", 18).into_font(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+// The scatter of `draw_clusters` under `caption`, with its own legend if `legend` is set
+fn draw_cluster_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    clusters: &HashMap<usize, Vec<String>>,
+    coordinates: &HashMap<String, (f64, f64)>,
+    x_desc: &str,
+    y_desc: &str,
+    legend: bool,
+) -> Result<(), AppError> {
+    let mut plotted = Vec::new();
+    for municipality in clusters.values().flatten() {
+        match coordinates.get(municipality) {
+            Some(&point) => plotted.push(point),
+            None => warn!("no coordinates for '{}', skipping", municipality),
+        }
+    }
+    let x_range = axis_range(plotted.iter().map(|&(x, _)| x));
+    let y_range = axis_range(plotted.iter().map(|&(_, y)| y));
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 40).into_font())
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc(y_desc)
+        .draw()?;
+
+    let mut cluster_ids: Vec<usize> = clusters.keys().copied().collect();
+    cluster_ids.sort_unstable();
+
+    for cluster in cluster_ids {
+        let color = cluster_color(cluster);
+        let mut cluster_coordinates = Vec::new();
+
+        for municipality in &clusters[&cluster] {
+            if let Some(&(x, y)) = coordinates.get(municipality) {
+                cluster_coordinates.push((x, y));
+            }
+        }
+
+        chart
+            .draw_series(
+                cluster_coordinates
+                    .into_iter()
+                    .map(|c| Circle::new(c, 5, color.filled())), // Choose the marker style
+            )?
+            .label(format!("Cluster {}", cluster))
+            .legend(move |(x, y)| Circle::new((x, y), 5, color.filled()));
+    }
+
+    if legend {
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+    }
+
+    Ok(())
+}
+
+/// Scatter plot of two categories against each other, drawing each municipality
+/// at `(weight(cat_x), weight(cat_y))` in the color of its cluster in
+/// `assignments` (black when it has none). Municipalities missing either
+/// category are left out. The axes are labelled with the categories' `names`,
+/// or "Category N" for those without one. With `log_scale` both axes are logarithmic, and
+/// municipalities with a non-positive weight are left out too. Fails with
+/// [`AppError::MissingCategory`] when no record has `cat_x` or `cat_y`. Paths
+/// ending in `.svg` are written as SVG, anything else as PNG.
+pub fn plot_categories<T: GraphData>(
+    data: &[T],
+    cat_x: i32,
+    cat_y: i32,
+    assignments: &HashMap<String, usize>,
+    names: &HashMap<i32, String>,
+    log_scale: bool,
+    path: &str,
+) -> Result<(), AppError> {
+    for category in [cat_x, cat_y] {
+        if !data
+            .iter()
+            .any(|entry| entry.get_weight(category).is_some())
+        {
+            return Err(AppError::MissingCategory(category));
+        }
+    }
+
+    let mut series: BTreeMap<Option<usize>, Vec<(f64, f64)>> = BTreeMap::new();
+    for entry in data {
+        let (Some(x), Some(y)) = (entry.get_weight(cat_x), entry.get_weight(cat_y)) else {
+            continue;
+        };
+        if log_scale && (x <= 0.0 || y <= 0.0) {
+            continue;
+        }
+        series
+            .entry(assignments.get(entry.municipality()).copied())
+            .or_default()
+            .push((x, y));
+    }
+
+    let desc = |category: i32| {
+        names
+            .get(&category)
+            .cloned()
+            .unwrap_or_else(|| format!("Category {}", category))
+    };
+    let x_desc = desc(cat_x);
+    let y_desc = desc(cat_y);
+
+    if path.ends_with(".svg") {
+        let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+        draw_categories(&root, &series, log_scale, &x_desc, &y_desc)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        draw_categories(&root, &series, log_scale, &x_desc, &y_desc)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn draw_categories<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    series: &BTreeMap<Option<usize>, Vec<(f64, f64)>>,
+    log_scale: bool,
+    x_desc: &str,
+    y_desc: &str,
+) -> Result<(), AppError> {
+    let xs = || series.values().flatten().map(|&(x, _)| x);
+    let ys = || series.values().flatten().map(|&(_, y)| y);
+
+    root.fill(&WHITE)?;
+    if log_scale {
+        let x_range = log_axis_range(xs());
+        let y_range = log_axis_range(ys());
+        draw_scatter(
+            root,
+            "Category Plot",
+            x_range.log_scale(),
+            y_range.log_scale(),
+            series,
+            x_desc,
+            y_desc,
+        )?;
+    } else {
+        let x_range = axis_range(xs());
+        let y_range = axis_range(ys());
+        draw_scatter(
+            root,
+            "Category Plot",
+            x_range,
+            y_range,
+            series,
+            x_desc,
+            y_desc,
+        )?;
+    }
+    Ok(())
+}
+
+// Draw one colored series per cluster (`None` for unclustered points) with a legend
+fn draw_scatter<DB, X, Y>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    x_spec: X,
+    y_spec: Y,
+    series: &BTreeMap<Option<usize>, Vec<(f64, f64)>>,
+    x_desc: &str,
+    y_desc: &str,
+) -> Result<(), AppError>
+where
+    DB: DrawingBackend,
+    X: AsRangedCoord<Value = f64>,
+    Y: AsRangedCoord<Value = f64>,
+    X::CoordDescType: ValueFormatter<f64>,
+    Y::CoordDescType: ValueFormatter<f64>,
+{
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 40).into_font())
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d(x_spec, y_spec)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc(y_desc)
+        .draw()?;
+
+    for (cluster, points) in series {
+        let (color, label) = match cluster {
+            Some(cluster) => (cluster_color(*cluster), format!("Cluster {}", cluster)),
+            None => (BLACK, "Unclustered".to_string()),
+        };
+        chart
+            .draw_series(points.iter().map(|&c| Circle::new(c, 5, color.filled())))?
+            .label(label)
+            .legend(move |(x, y)| Circle::new((x, y), 5, color.filled()));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+/// Map of the municipalities in `coords`, given as `(latitude, longitude)` in
+/// degrees, in an equirectangular projection: longitude on x and latitude on y.
+/// The axes span the bounding box of the points, widened so that a degree of
+/// longitude is drawn `cos(latitude)` times as long as one of latitude at the
+/// box's middle, which keeps shapes roughly true. Points are colored by their
+/// cluster in `assignments`, black when they have none; municipalities only in
+/// `assignments` have no known coordinates and are skipped. Paths ending in
+/// `.svg` are written as SVG, anything else as PNG.
+pub fn plot_map(
+    coords: &HashMap<String, (f64, f64)>,
+    assignments: &HashMap<String, usize>,
+    path: &str,
+) -> Result<(), AppError> {
+    let mut series: BTreeMap<Option<usize>, Vec<(f64, f64)>> = BTreeMap::new();
+    for (municipality, &(latitude, longitude)) in coords {
+        series
+            .entry(assignments.get(municipality).copied())
+            .or_default()
+            .push((longitude, latitude));
+    }
+    let (x_range, y_range) = equirectangular_ranges(series.values().flatten().copied());
+
+    if path.ends_with(".svg") {
+        let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+        root.fill(&WHITE)?;
+        draw_scatter(
+            &root,
+            "Map",
+            x_range,
+            y_range,
+            &series,
+            "Longitude",
+            "Latitude",
+        )?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        root.fill(&WHITE)?;
+        draw_scatter(
+            &root,
+            "Map",
+            x_range,
+            y_range,
+            &series,
+            "Longitude",
+            "Latitude",
+        )?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+// Roughly the width over the height of the plotting area of an 800x600 chart
+// once the caption, margins and label areas are taken off
+const MAP_ASPECT: f64 = 1.5;
+
+// Axis ranges around `(longitude, latitude)` points, the shorter one widened
+// about its middle until the map has the plotting area's proportions
+fn equirectangular_ranges(
+    points: impl Iterator<Item = (f64, f64)> + Clone,
+) -> (Range<f64>, Range<f64>) {
+    let x_range = axis_range(points.clone().map(|(x, _)| x));
+    let y_range = axis_range(points.map(|(_, y)| y));
+
+    let middle_latitude = (y_range.start + y_range.end) / 2.0;
+    // Near the poles a degree of longitude shrinks to nothing; keep some width
+    let longitude_scale = middle_latitude.to_radians().cos().max(0.1);
+    let width = (x_range.end - x_range.start) * longitude_scale;
+    let height = y_range.end - y_range.start;
+
+    let widen = |range: Range<f64>, span: f64| {
+        let middle = (range.start + range.end) / 2.0;
+        (middle - span / 2.0)..(middle + span / 2.0)
+    };
+    if width / height < MAP_ASPECT {
+        let x_span = height * MAP_ASPECT / longitude_scale;
+        (widen(x_range, x_span), y_range)
+    } else {
+        let y_span = width / MAP_ASPECT;
+        (x_range, widen(y_range, y_span))
+    }
+}
+
+/// Bar chart with one bar per cluster, as tall as the cluster has members and
+/// drawn in the cluster's color, ordered by cluster id. Paths ending in `.svg`
+/// are written as SVG, anything else as PNG.
+pub fn plot_cluster_sizes(
+    clusters: &HashMap<usize, Vec<String>>,
+    path: &str,
+) -> Result<(), AppError> {
+    if path.ends_with(".svg") {
+        let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+        draw_cluster_sizes(&root, clusters)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        draw_cluster_sizes(&root, clusters)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn draw_cluster_sizes<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    clusters: &HashMap<usize, Vec<String>>,
+) -> Result<(), AppError> {
+    let max_cluster = clusters.keys().copied().max().unwrap_or(0);
+    let max_size = clusters.values().map(Vec::len).max().unwrap_or(0);
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption("Cluster Sizes", ("sans-serif", 40).into_font())
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..max_cluster).into_segmented(), 0..max_size + 1)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Cluster")
+        .y_desc("Municipalities")
+        .draw()?;
+
+    let mut sizes: Vec<(usize, usize)> = clusters
+        .iter()
+        .map(|(&cluster, members)| (cluster, members.len()))
+        .collect();
+    sizes.sort_unstable();
+
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style_func(|segment, _| match segment {
+                SegmentValue::Exact(cluster) | SegmentValue::CenterOf(cluster) => {
+                    cluster_color(*cluster).filled()
+                }
+                SegmentValue::Last => BLACK.filled(),
+            })
+            .margin(2)
+            .data(sizes),
+    )?;
+    Ok(())
+}
+
+/// Bar chart of a degree distribution from `degree_distribution`, with one bar
+/// per degree from 0 to the largest one. `x_desc` labels the degree axis. Paths
+/// ending in `.svg` are written as SVG, anything else as PNG.
+pub fn plot_degree_distribution(
+    distribution: &HashMap<usize, usize>,
+    x_desc: &str,
+    file_path: &str,
+) -> Result<(), AppError> {
+    if file_path.ends_with(".svg") {
+        let root = SVGBackend::new(file_path, (800, 600)).into_drawing_area();
+        draw_degree_distribution(&root, distribution, x_desc)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(file_path, (800, 600)).into_drawing_area();
+        draw_degree_distribution(&root, distribution, x_desc)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn draw_degree_distribution<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    distribution: &HashMap<usize, usize>,
+    x_desc: &str,
+) -> Result<(), AppError> {
+    let max_degree = distribution.keys().copied().max().unwrap_or(0);
+    let max_count = distribution.values().copied().max().unwrap_or(0);
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(root)
+        .caption("Degree Distribution", ("sans-serif", 40).into_font())
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..max_degree).into_segmented(), 0..max_count + 1)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc("Municipalities")
+        .draw()?;
+
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style(cluster_color(0).filled())
+            .margin(2)
+            .data(distribution.iter().map(|(&degree, &count)| (degree, count))),
+    )?;
+    Ok(())
+}
+
+/// Histogram of the weights of `category` over `bins` equally wide bins
+/// spanning their range (see [`histogram`]), to show skew, several peaks or
+/// outliers before clustering. The axis is labeled with the category's name in
+/// `names`, or "Category N" without one. Paths ending in `.svg` are written as
+/// SVG, anything else as PNG.
+pub fn plot_histogram<T: GraphData>(
+    data: &[T],
+    category: i32,
+    bins: usize,
+    names: &HashMap<i32, String>,
+    path: &str,
+) -> Result<(), AppError> {
+    let counts = histogram(data, category, bins);
+    if counts.is_empty() {
+        return Err(AppError::MissingCategory(category));
+    }
+    let desc = names
+        .get(&category)
+        .cloned()
+        .unwrap_or_else(|| format!("Category {}", category));
+
+    if path.ends_with(".svg") {
+        let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+        draw_histogram(&root, &desc, &counts)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        draw_histogram(&root, &desc, &counts)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn draw_histogram<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    desc: &str,
+    counts: &[(f64, f64, usize)],
+) -> Result<(), AppError> {
+    root.fill(&WHITE)?;
+
+    let x_range = axis_range(counts.iter().flat_map(|&(start, end, _)| [start, end]));
+    let max_count = counts.iter().map(|&(_, _, count)| count).max().unwrap_or(0);
+    let mut chart = ChartBuilder::on(root)
+        .caption(
+            format!("{} Distribution", desc),
+            ("sans-serif", 40).into_font(),
+        )
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(60)
+        .build_cartesian_2d(x_range, 0..max_count + 1)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(desc)
+        .y_desc("Municipalities")
+        .draw()?;
+
+    // A single value gets a bin of zero width; give it a visible one
+    let (first, last) = (counts[0].0, counts[counts.len() - 1].1);
+    let min_width = if last > first { 0.0 } else { 0.1 };
+    chart.draw_series(counts.iter().map(|&(start, end, count)| {
+        Rectangle::new(
+            [(start - min_width, 0), (end + min_width, count)],
+            cluster_color(0).filled(),
+        )
+    }))?;
+    Ok(())
+}
+
+/// Line chart of a k-means run's inertia per iteration, from
+/// [`ClusteringResult::inertia_history`](crate::ClusteringResult::inertia_history).
+/// A curve that flattens early has converged; one still falling at the end
+/// needs more `max_iterations`. Paths ending in `.svg` are written as SVG,
+/// anything else as PNG.
+pub fn plot_convergence(history: &[f64], path: &str) -> Result<(), AppError> {
+    if path.ends_with(".svg") {
+        let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+        draw_convergence(&root, history)?;
+        root.present()?;
+    } else {
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        draw_convergence(&root, history)?;
+        root.present()?;
+    }
+    Ok(())
+}
+
+fn draw_convergence<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    history: &[f64],
+) -> Result<(), AppError> {
+    root.fill(&WHITE)?;
+
+    // Iterations count from 1, with a step of room on either side
+    let mut chart = ChartBuilder::on(root)
+        .caption("Convergence", ("sans-serif", 40).into_font())
+        .margin(10)
+        .x_label_area_size(50)
+        .y_label_area_size(80)
+        .build_cartesian_2d(0..history.len() + 1, axis_range(history.iter().copied()))?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Iteration")
+        .y_desc("Inertia")
+        .draw()?;
+
+    let points = (1..).zip(history.iter().copied());
+    chart.draw_series(LineSeries::new(points.clone(), cluster_color(0)))?;
+    chart.draw_series(points.map(|point| Circle::new(point, 3, cluster_color(0).filled())))?;
+    Ok(())
+}
+
+// Like `axis_range`, but padding by a factor so the range stays positive for a log axis
+fn log_axis_range(values: impl Iterator<Item = f64>) -> Range<f64> {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+
+    if min > max {
+        return 1.0..10.0;
+    }
+    (min / 1.1)..(max * 1.1)
+}
+
+/// The span of `values` padded by 5% on each side. A single repeated value is
+/// widened to ±1 around it, and no values at all give `0..1`.
+pub fn axis_range(values: impl Iterator<Item = f64>) -> Range<f64> {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+
+    if min > max {
+        return 0.0..1.0;
+    }
+    if min == max {
+        return (min - 1.0)..(max + 1.0);
+    }
+
+    let padding = (max - min) * 0.05;
+    (min - padding)..(max + padding)
+}
+
+// Colors for cluster ids, reused from the start once there are more clusters than colors
+const CLUSTER_COLORS: [RGBColor; 10] = [
+    RGBColor(31, 119, 180),
+    RGBColor(255, 127, 14),
+    RGBColor(44, 160, 44),
+    RGBColor(214, 39, 40),
+    RGBColor(148, 103, 189),
+    RGBColor(140, 86, 75),
+    RGBColor(227, 119, 194),
+    RGBColor(127, 127, 127),
+    RGBColor(188, 189, 34),
+    RGBColor(23, 190, 207),
+];
+
+/// The color used for `cluster` in every cluster plot.
+pub fn cluster_color(cluster: usize) -> RGBColor {
+    CLUSTER_COLORS[cluster % CLUSTER_COLORS.len()]
+}
+
+/// Map each municipality to the first two dimensions of its feature vector.
+/// `features` must be in the same order as `data`; a missing second dimension is `0.0`.
+pub fn feature_coordinates<T: GraphData>(
+    data: &[T],
+    features: &[Vec<f64>],
+) -> HashMap<String, (f64, f64)> {
+    data.iter()
+        .zip(features)
+        .map(|(entry, feature)| {
+            let x = feature.first().copied().unwrap_or(0.0);
+            let y = feature.get(1).copied().unwrap_or(0.0);
+            (entry.municipality().to_string(), (x, y))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixture, temp_path};
+    use crate::{read_input, EducationData};
+
+    #[test]
+    fn convergence_plot_is_written_as_svg() {
+        let history = [12.0, 7.5, 6.25, 6.0];
+        let path = &temp_path("convergence.svg");
+        plot_convergence(&history, path).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().contains("<svg"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cluster_plot_axes_cover_every_point() {
+        let coordinates: HashMap<String, (f64, f64)> = [
+            ("A", (150.0, -20.0)),
+            ("B", (0.5, 3.0)),
+            ("C", (75.0, 140.0)),
+        ]
+        .iter()
+        .map(|&(name, point)| (name.to_string(), point))
+        .collect();
+        let x_range = axis_range(coordinates.values().map(|&(x, _)| x));
+        let y_range = axis_range(coordinates.values().map(|&(_, y)| y));
+        for &(x, y) in coordinates.values() {
+            assert!(
+                x_range.start < x && x < x_range.end,
+                "{} outside {:?}",
+                x,
+                x_range
+            );
+            assert!(
+                y_range.start < y && y < y_range.end,
+                "{} outside {:?}",
+                y,
+                y_range
+            );
+        }
+        assert_eq!(axis_range([4.0, 4.0].into_iter()), 3.0..5.0);
+        assert_eq!(axis_range(std::iter::empty()), 0.0..1.0);
+
+        let clusters = HashMap::from([
+            (0, vec!["A".to_string(), "B".to_string()]),
+            (1, vec!["C".to_string()]),
+        ]);
+        let path = &temp_path("clusters.svg");
+        plot_clusters(&clusters, &coordinates, "x", "y", path).unwrap();
+        let svg = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(svg.matches("<circle").count() >= 3);
+    }
+
+    #[test]
+    fn cluster_plots_ending_in_svg_are_svg_files() {
+        let clusters = HashMap::from([
+            (0, vec!["A".to_string(), "B".to_string()]),
+            (1, vec!["C".to_string()]),
+        ]);
+        let coordinates: HashMap<String, (f64, f64)> =
+            [("A", (1.0, 2.0)), ("B", (2.0, 1.0)), ("C", (8.0, 9.0))]
+                .iter()
+                .map(|&(name, point)| (name.to_string(), point))
+                .collect();
+
+        let path = &temp_path("cluster-sizes.svg");
+        plot_cluster_sizes(&clusters, path).unwrap();
+        let sizes = std::fs::read_to_string(path).unwrap();
+        plot_clusters(&clusters, &coordinates, "x", "y", path).unwrap();
+        let scatter = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(sizes.starts_with("<svg"));
+        assert!(scatter.starts_with("<svg"));
+    }
+
+    #[test]
+    fn histogram_plot_is_written_as_svg() {
+        let education = read_input::<EducationData>(&fixture("education.json")).unwrap();
+        let path = &temp_path("histogram.svg");
+        let names = HashMap::from([(1, "Literacy".to_string())]);
+        plot_histogram(&education, 1, 5, &names, path).unwrap();
+        let svg = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Literacy Distribution"));
+        assert!(matches!(
+            plot_histogram(&education, 99, 5, &HashMap::new(), path),
+            Err(AppError::MissingCategory(99))
+        ));
+    }
+}
@@ -0,0 +1,317 @@
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::{
+    category_counts, find_by_municipality, fmt_f64, most_ambiguous, rank_trends, shortest_path,
+    summarize, top_n, ClusteringResult, Dataset, GraphConfig, GraphData, Summary,
+};
+
+/// Print each category of `dataset`, named when its key has it, with the
+/// number of municipalities that have it.
+pub fn print_category_counts<T: GraphData>(
+    out: &mut impl Write,
+    dataset: &Dataset<T>,
+) -> io::Result<()> {
+    for (category, count) in category_counts(&dataset.records) {
+        match dataset.key.get(&category) {
+            Some(name) => writeln!(out, "  {} ({}): {} municipalities", category, name, count)?,
+            None => writeln!(out, "  {}: {} municipalities", category, count)?,
+        }
+    }
+    Ok(())
+}
+
+/// Print how many municipalities of `dataset` have all of its categories.
+pub fn print_completeness<T>(out: &mut impl Write, dataset: &Dataset<T>) -> io::Result<()> {
+    let completeness = dataset.completeness;
+    writeln!(
+        out,
+        "{} Completeness: {} of {} municipalities have all {} categories",
+        dataset.label, completeness.complete, completeness.municipalities, completeness.categories
+    )
+}
+
+/// Print every category of the record of the municipality called `name`
+/// (ignoring case), with its name, value and year.
+pub fn print_record<T: GraphData>(
+    out: &mut impl Write,
+    dataset: &Dataset<T>,
+    name: &str,
+    precision: usize,
+) -> io::Result<()> {
+    writeln!(out, "{} Record:", dataset.label)?;
+    let Some(record) = find_by_municipality(&dataset.records, name) else {
+        return writeln!(out, "  (not found)");
+    };
+    let categories = record.categories();
+    let names = dataset.category_names(&categories);
+    writeln!(out, "  {}", record.municipality())?;
+    for (category, name) in categories.iter().zip(names) {
+        if let (Some(value), Some(year)) =
+            (record.get_weight(*category), record.get_year(*category))
+        {
+            writeln!(
+                out,
+                "    {}: {} ({})",
+                name,
+                fmt_f64(value, precision),
+                year
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the [`top_n`] ranking of `n` municipalities of `dataset` by
+/// `category` under the category's name, numbered from 1.
+pub fn print_top_n<T: GraphData>(
+    out: &mut impl Write,
+    dataset: &Dataset<T>,
+    category: i32,
+    n: usize,
+    precision: usize,
+) -> io::Result<()> {
+    let ranked = top_n(&dataset.records, category, n);
+    match dataset.key.get(&category) {
+        Some(name) => writeln!(out, "{} Top {} by {}:", dataset.label, ranked.len(), name)?,
+        None => writeln!(
+            out,
+            "{} Top {} by Category {}:",
+            dataset.label,
+            ranked.len(),
+            category
+        )?,
+    }
+    if ranked.is_empty() {
+        writeln!(out, "  (no municipality has this category)")?;
+    }
+    for (rank, (municipality, weight)) in ranked.iter().enumerate() {
+        writeln!(
+            out,
+            "  {}. {}: {}",
+            rank + 1,
+            municipality,
+            fmt_f64(*weight, precision)
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the per-municipality [`summarize`] of `dataset` as a table sorted
+/// by name.
+pub fn print_summary<T: GraphData>(
+    out: &mut impl Write,
+    dataset: &Dataset<T>,
+    precision: usize,
+) -> io::Result<()> {
+    let summaries = summarize(&dataset.records, &dataset.categories());
+    let mut rows: Vec<(&String, &Summary)> = summaries.iter().collect();
+    rows.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    writeln!(out, "{} Summary:", dataset.label)?;
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(12);
+    writeln!(
+        out,
+        "  {:<width$} {:>12} {:>12} {:>12} {:>6}",
+        "Municipality", "Mean", "Min", "Max", "Count"
+    )?;
+    for (name, summary) in rows {
+        writeln!(
+            out,
+            "  {:<width$} {:>12} {:>12} {:>12} {:>6}",
+            name,
+            fmt_f64(summary.mean, precision),
+            fmt_f64(summary.min, precision),
+            fmt_f64(summary.max, precision),
+            summary.count
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the five steepest rising and falling [`rank_trends`] of `dataset`,
+/// if any municipality has a trend.
+pub fn print_trends<T: GraphData>(
+    out: &mut impl Write,
+    dataset: &Dataset<T>,
+    precision: usize,
+) -> io::Result<()> {
+    let trends = rank_trends(&dataset.records, &[]);
+    if trends.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{} Trends (steepest growth):", dataset.label)?;
+    for (municipality, slope) in trends.iter().take(5) {
+        writeln!(out, "  {}: {}", municipality, fmt_f64(*slope, precision))?;
+    }
+    writeln!(out, "{} Trends (steepest decline):", dataset.label)?;
+    for (municipality, slope) in trends.iter().rev().take(5) {
+        writeln!(out, "  {}: {}", municipality, fmt_f64(*slope, precision))?;
+    }
+    Ok(())
+}
+
+/// Print how many connected components a graph has, the sizes of those with
+/// more than one municipality (largest first) and how many municipalities are
+/// isolated.
+pub fn print_components(
+    out: &mut impl Write,
+    label: &str,
+    components: &[Vec<NodeIndex>],
+) -> io::Result<()> {
+    let mut sizes: Vec<usize> = components
+        .iter()
+        .map(Vec::len)
+        .filter(|&size| size > 1)
+        .collect();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    writeln!(
+        out,
+        "{} Components: {} (sizes: {:?}, isolated: {})",
+        label,
+        components.len(),
+        sizes,
+        components.len() - sizes.len()
+    )
+}
+
+/// Print the ten municipalities with the highest scores, best first.
+pub fn print_top_ranks(
+    out: &mut impl Write,
+    label: &str,
+    ranks: &HashMap<String, f64>,
+    precision: usize,
+) -> io::Result<()> {
+    let mut ranked: Vec<(&String, &f64)> = ranks.iter().collect();
+    ranked.sort_unstable_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    writeln!(out, "{} (top 10):", label)?;
+    for (municipality, rank) in ranked.into_iter().take(10) {
+        writeln!(out, "  {}: {}", municipality, fmt_f64(*rank, precision))?;
+    }
+    Ok(())
+}
+
+/// Print the municipalities along the [`shortest_path`] of `graph`, built
+/// with `config`, and its cost, or that there is none.
+pub fn print_path(
+    out: &mut impl Write,
+    label: &str,
+    graph: &DiGraph<&str, f64>,
+    from: &str,
+    to: &str,
+    config: &GraphConfig,
+    precision: usize,
+) -> io::Result<()> {
+    match shortest_path(graph, from, to, config) {
+        Some((names, cost)) => writeln!(
+            out,
+            "{} path from {} to {}: {} (cost {})",
+            label,
+            from,
+            to,
+            names.join(" -> "),
+            fmt_f64(cost, precision)
+        ),
+        None => writeln!(out, "{} path from {} to {}: none", label, from, to),
+    }
+}
+
+/// Print every cluster of `result` with its size, its centroid per named
+/// category and its members in alphabetical order.
+pub fn print_cluster_report(
+    out: &mut impl Write,
+    label: &str,
+    result: &ClusteringResult,
+    category_names: &[String],
+    precision: usize,
+) -> io::Result<()> {
+    writeln!(out, "{} Clusters:", label)?;
+    for (cluster, centroid) in result.centroids.iter().enumerate() {
+        let mut members: Vec<&str> = result
+            .assignments
+            .get(&cluster)
+            .map(|members| members.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        members.sort_unstable();
+
+        writeln!(
+            out,
+            "  Cluster {}: {} municipalities",
+            cluster,
+            members.len()
+        )?;
+        for (name, value) in category_names.iter().zip(centroid) {
+            writeln!(out, "    {}: {}", name, fmt_f64(*value, precision))?;
+        }
+        writeln!(out, "    Members: {}", members.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Print how many iterations a k-means run took and whether it converged.
+pub fn print_iterations(
+    out: &mut impl Write,
+    label: &str,
+    result: &ClusteringResult,
+) -> io::Result<()> {
+    if result.converged {
+        writeln!(
+            out,
+            "{} Iterations: {} (converged)",
+            label, result.iterations
+        )
+    } else {
+        writeln!(
+            out,
+            "{} Iterations: {} (stopped before converging)",
+            label, result.iterations
+        )
+    }
+}
+
+/// Print the municipality each k-medoids cluster is centered on.
+pub fn print_medoids(out: &mut impl Write, result: &ClusteringResult) -> io::Result<()> {
+    for (cluster, &medoid) in result.medoids.iter().enumerate() {
+        writeln!(
+            out,
+            "  Cluster {}: {}",
+            cluster, result.municipalities[medoid]
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the `n` municipalities with the most evenly split fuzzy
+/// `memberships`, with their entropy and memberships from largest to smallest.
+pub fn print_ambiguous(
+    out: &mut impl Write,
+    municipalities: &[String],
+    memberships: &[Vec<f64>],
+    n: usize,
+    precision: usize,
+) -> io::Result<()> {
+    let rows: HashMap<&str, &Vec<f64>> = municipalities
+        .iter()
+        .map(String::as_str)
+        .zip(memberships)
+        .collect();
+    for (municipality, entropy) in most_ambiguous(municipalities, memberships, n) {
+        let mut row = rows[municipality.as_str()].clone();
+        row.sort_unstable_by(|a, b| b.total_cmp(a));
+        let row: Vec<String> = row.iter().map(|&p| fmt_f64(p, precision)).collect();
+        writeln!(
+            out,
+            "  {}: entropy {} (memberships {})",
+            municipality,
+            fmt_f64(entropy, precision),
+            row.join(", ")
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use finalproject::{
+    filter_common_municipalities, filter_common_municipalities_with, joint_features,
+    k_means_clustering, k_means_features, k_medoids, read_input, restrict_to_common, standardize,
+    AppError, ClusteringResult, EducationData, GraphData, KMeansConfig, NameNormalization,
+    PopGrowthData,
+};
+
+// Both fixtures hold two well separated groups, "Alpha_*" and "Beta_*", plus
+// one municipality only they contain. The population growth fixture spells
+// Alpha_East as " alpha_east ", which only a loose name comparison matches.
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn education() -> Vec<EducationData> {
+    read_input(&fixture("education.json")).unwrap()
+}
+
+fn pop_growth() -> Vec<PopGrowthData> {
+    read_input(&fixture("popgrowth.json")).unwrap()
+}
+
+fn members(result: &ClusteringResult, cluster: usize) -> Vec<&str> {
+    let mut members: Vec<&str> = result.assignments[&cluster]
+        .iter()
+        .map(String::as_str)
+        .collect();
+    members.sort();
+    members
+}
+
+#[test]
+fn reads_fixtures() {
+    let education = education();
+    let pop_growth = pop_growth();
+    assert_eq!(education.len(), 6);
+    assert_eq!(pop_growth.len(), 6);
+    assert_eq!(education[0].municipality(), "Alpha_North");
+    assert_eq!(education[3].get_weight(1), Some(100.0));
+    assert_eq!(pop_growth[0].get_weight(2), Some(5.9));
+}
+
+#[test]
+fn join_pairs_exact_names_in_first_dataset_order() {
+    let common = filter_common_municipalities(&education(), &pop_growth());
+    let names: Vec<&str> = common.iter().map(|(name, _, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Alpha_North", "Alpha_South", "Beta_North", "Beta_South"]
+    );
+    for (name, edu, pop) in &common {
+        assert_eq!(edu.municipality(), name);
+        assert_eq!(pop.municipality(), name);
+    }
+}
+
+#[test]
+fn loose_join_matches_differently_spelled_names() {
+    let common =
+        filter_common_municipalities_with(&education(), &pop_growth(), NameNormalization::LOOSE);
+    let (name, _, pop) = common
+        .iter()
+        .find(|(name, _, _)| name == "Alpha_East")
+        .unwrap();
+    assert_eq!(name, "Alpha_East");
+    assert_eq!(pop.get_weight(1), Some(0.9));
+    assert_eq!(common.len(), 5);
+}
+
+#[test]
+fn restrict_to_common_renames_second_dataset() {
+    let (education, pop_growth) =
+        restrict_to_common(&education(), &pop_growth(), NameNormalization::LOOSE);
+    assert_eq!(education.len(), 5);
+    assert_eq!(pop_growth.len(), 5);
+    for (edu, pop) in education.iter().zip(&pop_growth) {
+        assert_eq!(edu.municipality(), pop.municipality());
+    }
+}
+
+#[test]
+fn pipeline_clusters_joined_features() {
+    let (municipalities, mut features) = joint_features(&education(), &pop_growth(), &[1, 2]);
+    standardize(&mut features);
+    let result = k_means_features(&municipalities, &features, &KMeansConfig::new(2)).unwrap();
+
+    // Clusters are numbered by their alphabetically first member
+    assert_eq!(members(&result, 0), ["Alpha_North", "Alpha_South"]);
+    assert_eq!(members(&result, 1), ["Beta_North", "Beta_South"]);
+    assert!(result.converged);
+}
+
+#[test]
+fn pipeline_clusters_one_dataset_after_restricting() {
+    let (education, _) = restrict_to_common(&education(), &pop_growth(), NameNormalization::LOOSE);
+    let result = k_means_clustering(&education, &[1, 2], &[], true, &KMeansConfig::new(2)).unwrap();
+
+    assert_eq!(
+        members(&result, 0),
+        ["Alpha_East", "Alpha_North", "Alpha_South"]
+    );
+    assert_eq!(members(&result, 1), ["Beta_North", "Beta_South"]);
+}
+
+#[test]
+fn k_medoids_agrees_with_k_means() {
+    let (municipalities, features) = joint_features(&education(), &pop_growth(), &[1, 2]);
+    let result = k_medoids(&municipalities, &features, 2, 0).unwrap();
+
+    assert_eq!(members(&result, 0), ["Alpha_North", "Alpha_South"]);
+    assert_eq!(members(&result, 1), ["Beta_North", "Beta_South"]);
+    assert_eq!(result.medoids.len(), 2);
+}
+
+#[test]
+fn too_many_clusters_is_an_error() {
+    let (municipalities, features) = joint_features(&education(), &pop_growth(), &[1, 2]);
+    let err = k_means_features(&municipalities, &features, &KMeansConfig::new(5)).unwrap_err();
+    assert!(matches!(err, AppError::ClusterCount { k: 5, records: 4 }));
+}
+
+#[test]
+fn stats_only_writes_nothing_and_keeps_read_errors() {
+    let out_dir = std::env::temp_dir().join(format!("stats-only-{}", std::process::id()));
+    let run = |education: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_finalproject"))
+            .args(["--stats-only", "--clusters", "2", "--education", education])
+            .args(["--popgrowth", &fixture("popgrowth.json")])
+            .arg("--out-dir")
+            .arg(&out_dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&fixture("education.json"));
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Education Clusters:"));
+    assert!(!stdout.contains("Would write"));
+    assert!(!out_dir.exists());
+
+    let output = run(&fixture("missing.json"));
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("could not open"));
+}
+
+#[test]
+fn subcommands_run_only_their_step() {
+    let run = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_finalproject"))
+            .args(args)
+            .args(["--education", &fixture("education.json")])
+            .args(["--popgrowth", &fixture("popgrowth.json")])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stats = run(&["stats", "--stats-only"]);
+    assert!(stats.contains("Education Summary:"));
+    assert!(!stats.contains("PageRank"));
+    assert!(!stats.contains("Education Clusters:"));
+
+    let clusters = run(&["cluster", "--stats-only", "--clusters", "2"]);
+    assert!(clusters.contains("Education Clusters:"));
+    assert!(!clusters.contains("Education Summary:"));
+    assert!(!clusters.contains("PageRank"));
+
+    let path = run(&["path", "Alpha_North", "Beta_North"]);
+    assert!(path.contains("Education path from Alpha_North to Beta_North:"));
+    assert!(!path.contains("PageRank"));
+    assert!(!path.contains("Education Summary:"));
+
+    // Without a subcommand every step runs, as before
+    let all = run(&["--stats-only", "--clusters", "2"]);
+    assert!(all.contains("Education Summary:"));
+    assert!(all.contains("PageRank"));
+    assert!(all.contains("Education Clusters:"));
+}
+
+#[test]
+fn subcommands_reject_flags_of_other_steps() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_finalproject"))
+        .args(["stats", "--clusters", "2"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn geo_clusters_come_from_the_coordinates_file() {
+    let run = |extra: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_finalproject"))
+            .args([
+                "cluster",
+                "--stats-only",
+                "--clusters",
+                "2",
+                "--geo-clusters",
+                "2",
+            ])
+            .args(["--education", &fixture("education.json")])
+            .args(["--popgrowth", &fixture("popgrowth.json")])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", extra);
+        output
+    };
+
+    let output = run(&["--coordinates", &fixture("coordinates.csv")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Geographic Inertia (km²):"));
+    assert!(stdout.contains("Geographic Medoids:"));
+
+    // Without the file there is nothing to place the municipalities by
+    let output = run(&[]);
+    assert!(!String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Geographic Inertia"));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--geo-clusters needs --coordinates"));
+}
+
+#[test]
+fn the_same_seed_repeats_the_clustering() {
+    let run = |seed: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_finalproject"))
+            .args(["cluster", "--stats-only", "--clusters", "2", "--seed", seed])
+            .args(["--education", &fixture("education.json")])
+            .args(["--popgrowth", &fixture("popgrowth.json")])
+            .output()
+            .unwrap()
+    };
+    // The clusters print in hash map order, so compare the scores instead
+    let scores = |output: std::process::Output| -> Vec<String> {
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .filter(|line| line.contains("Inertia:") || line.contains("Silhouette:"))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let first = scores(run("7"));
+    assert!(!first.is_empty());
+    assert_eq!(first, scores(run("7")));
+    assert!(!run("seven").status.success());
+}